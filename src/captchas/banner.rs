@@ -0,0 +1,175 @@
+//! 在输出图片的顶部或底部添加包含提示文本的横幅，使图片在丢失文字说明时仍然可以理解。
+
+use crate::{
+    captchas::prompt,
+    errors::{Error, Result},
+    models::payload::SpecialPayload,
+    provider::manifest::Manifest,
+    vars::LANG_FALLBACKS,
+};
+use magick_rust::{DrawingWand, GravityType, MagickWand, PixelWand};
+use serde::Deserialize;
+
+const FALLBACK_LANG: &str = "en";
+const FALLBACK_COLOR: &str = "white";
+const FALLBACK_BACKGROUND: &str = "black";
+const MIN_HEIGHT: usize = 24;
+// 默认字体不保证包含其字形的语言（主标签）
+const CJK_LANGS: [&str; 3] = ["zh", "ja", "ko"];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Banner {
+    // 位置
+    pub position: Option<Position>,
+    // 提示文本的语言，指定了字体家族时默认为语言回退链中的第一个，否则默认为英文
+    pub lang: Option<String>,
+    // 横幅高度
+    pub height: Option<usize>,
+    // 字体家族（网格验证默认使用水印字体家族，图片验证默认使用 ImageMagick 的默认字体），中日韩语言必须指定
+    pub font_family: Option<String>,
+    // 字体大小
+    pub font_size: Option<f64>,
+    // 文字颜色
+    pub color: Option<String>,
+    // 背景颜色
+    pub background: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Position {
+    #[default]
+    Header,
+    Footer,
+}
+
+impl Banner {
+    /// 检查横幅选项：中日韩语言需要指定包含其字形的字体家族。
+    pub fn check(&self) -> Result<()> {
+        if self.font_family.is_none() && is_cjk(self.lang()) {
+            return Err(Error::InvalidOutput(format!(
+                "banner font_family is required for language: {}",
+                self.lang()
+            )));
+        }
+
+        Ok(())
+    }
+
+    // 未指定字体家族时，默认字体只能保证覆盖英文
+    fn lang(&self) -> &str {
+        match (&self.lang, &self.font_family) {
+            (Some(lang), _) => lang,
+            (None, Some(_)) => LANG_FALLBACKS
+                .first()
+                .map(String::as_str)
+                .unwrap_or(FALLBACK_LANG),
+            (None, None) => FALLBACK_LANG,
+        }
+    }
+}
+
+fn is_cjk(lang: &str) -> bool {
+    let primary = lang.split(['-', '_']).next().unwrap_or_default();
+
+    CJK_LANGS
+        .iter()
+        .any(|cjk| primary.eq_ignore_ascii_case(cjk))
+}
+
+/// 扩展画布并绘制横幅，未指定字体家族时使用 `fallback_font_family`（都未指定时不设置字体）。
+pub fn draw(
+    wand: &mut MagickWand,
+    banner: &Banner,
+    payload: &SpecialPayload,
+    manifest: Option<&Manifest>,
+    fallback_font_family: Option<&str>,
+) -> Result<()> {
+    banner.check()?;
+    let lang = banner.lang();
+    let prompt = prompt::render(payload, manifest, Some(&[lang.to_string()]));
+    let Some(text) = prompt.get(lang) else {
+        // 没有可用的模板
+        return Ok(());
+    };
+
+    let width = wand.get_image_width();
+    let height = wand.get_image_height();
+    let banner_height = banner.height.unwrap_or(calculate_height(width));
+    let font_size = banner.font_size.unwrap_or(banner_height as f64 * 0.5);
+    let position = banner.position.unwrap_or_default();
+
+    // 使用背景色扩展画布
+    let mut background = PixelWand::new();
+    background.set_color(banner.background.as_deref().unwrap_or(FALLBACK_BACKGROUND))?;
+    wand.set_image_background_color(&background)?;
+    let (offset_y, gravity) = match position {
+        Position::Header => (-(banner_height as isize), GravityType::North),
+        Position::Footer => (0, GravityType::South),
+    };
+    wand.extend_image(width, height + banner_height, 0, offset_y)?;
+
+    let mut draw = DrawingWand::new();
+    let mut fill = PixelWand::new();
+    fill.set_color(banner.color.as_deref().unwrap_or(FALLBACK_COLOR))?;
+    if let Some(font_family) = banner.font_family.as_deref().or(fallback_font_family) {
+        draw.set_font_family(font_family)?;
+    }
+    draw.set_font_size(font_size);
+    draw.set_fill_color(&fill);
+    draw.set_gravity(gravity);
+    // 在横幅区域内垂直居中
+    let margin = ((banner_height as f64 - font_size) / 2.0).max(0.0);
+    draw.draw_annotation(0.0, margin, text)?;
+    wand.draw_image(&draw)?;
+
+    Ok(())
+}
+
+fn calculate_height(width: usize) -> usize {
+    (width / 12).max(MIN_HEIGHT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn banner(lang: Option<&str>, font_family: Option<&str>) -> Banner {
+        Banner {
+            position: None,
+            lang: lang.map(str::to_string),
+            height: None,
+            font_family: font_family.map(str::to_string),
+            font_size: None,
+            color: None,
+            background: None,
+        }
+    }
+
+    #[test]
+    fn test_lang() {
+        // 默认设置下使用英文，不依赖默认字体的中日韩字形
+        let default = banner(None, None);
+        assert_eq!(default.lang(), FALLBACK_LANG);
+        assert!(default.check().is_ok());
+        // 指定字体家族后使用语言回退链中的第一个
+        let with_font = banner(None, Some("Noto Sans CJK SC"));
+        assert_eq!(with_font.lang(), LANG_FALLBACKS[0]);
+        assert!(with_font.check().is_ok());
+        // 中日韩语言必须指定字体家族
+        assert!(matches!(
+            banner(Some("zh-hans"), None).check(),
+            Err(Error::InvalidOutput(_))
+        ));
+        assert!(matches!(
+            banner(Some("ja"), None).check(),
+            Err(Error::InvalidOutput(_))
+        ));
+        assert!(
+            banner(Some("zh-hans"), Some("Noto Sans CJK SC"))
+                .check()
+                .is_ok()
+        );
+        assert!(banner(Some("en"), None).check().is_ok());
+    }
+}
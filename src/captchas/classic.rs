@@ -1,22 +1,27 @@
 use crate::{
-    captchas::{Created, check_out_base, namegen},
+    captchas::{
+        Created, check_out_base,
+        output::{self, OutputControl},
+    },
     errors::Result,
-    fail,
     models::{params::ChoicesControl, payload::SpecialPayload},
 };
 use captcha_rs::CaptchaBuilder;
+use magick_rust::{MagickWand, PixelWand, magick_wand_genesis};
 use rand::{Rng, seq::SliceRandom};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, sync::Once};
 
 const FALLBACK_LENGTH: usize = 5;
 const FALLBACK_WIDTH: u32 = 130;
 const FALLBACK_HEIGHT: u32 = 40;
 const FALLBACK_DARK_MODE: bool = false;
 const FALLBACK_COMPLEXITY: u32 = 5; // min: 1, max: 10
+const MAX_COMPLEXITY: u32 = 10;
 const FALLBACK_COMPRESSION: u8 = 40; // min: 1, max: 99
 const FALLBACK_WITH_CHOICES: bool = false;
 const FALLBACK_CHOICES_COUNT: usize = 6;
+const ESCALATED_MAX_CHOICES_COUNT: usize = 8;
 
 #[derive(Debug, Clone, Default, Deserialize)]
 pub struct Params {
@@ -41,9 +46,12 @@ pub struct Payload {
     pub choices: Vec<String>,
 }
 
+static MAGICK_START: Once = Once::new();
+
 pub fn create(
     out_base: &str,
     choices_control: &ChoicesControl,
+    output: &OutputControl,
     params: &Params,
 ) -> Result<Created> {
     check_out_base(out_base)?;
@@ -56,12 +64,24 @@ pub fn create(
         .compression(params.compression.unwrap_or(FALLBACK_COMPRESSION)) // min: 1, max: 99
         .build();
 
-    let file_name = format!("{}.jpg", namegen());
-    let out_file = PathBuf::from(out_base).join(&file_name);
-    captcha
-        .image
-        .save(&out_file)
-        .map_err(|e| fail!("failed to save captcha image: {}", e))?;
+    // 通过 MagickWand 按输出选项编码（未指定质量时使用编码器的默认质量）
+    MAGICK_START.call_once(magick_wand_genesis);
+    let pixels = captcha.image.to_rgb8();
+    let mut wand = MagickWand::new();
+    wand.new_image(
+        pixels.width() as usize,
+        pixels.height() as usize,
+        &PixelWand::new(),
+    )?;
+    wand.import_image_pixels(
+        0,
+        0,
+        pixels.width() as usize,
+        pixels.height() as usize,
+        pixels.as_raw(),
+        "RGB",
+    )?;
+    let file_name = output::write(&mut wand, out_base, output)?;
 
     let choices = if choices_control
         .with_choices
@@ -88,7 +108,7 @@ pub fn create(
     }
 
     Ok(Created {
-        file_name,
+        file_name: Some(file_name),
         right_index,
         payload: SpecialPayload::Classic(Payload {
             text: captcha.text,
@@ -97,6 +117,20 @@ pub fn create(
     })
 }
 
+/// 根据用户近期的失败次数提升难度：更高的复杂度，更多的候选项。
+pub fn escalate(params: &mut Params, choices_control: &mut ChoicesControl, level: usize) {
+    if level == 0 {
+        return;
+    }
+    let complexity = params.complexity.unwrap_or(FALLBACK_COMPLEXITY);
+    params.complexity = Some((complexity + level as u32 * 2).min(MAX_COMPLEXITY));
+    let choices_count = choices_control
+        .choices_count
+        .unwrap_or(FALLBACK_CHOICES_COUNT);
+    choices_control.choices_count =
+        Some((choices_count + level).min(ESCALATED_MAX_CHOICES_COUNT.max(choices_count)));
+}
+
 /// 生成与正确答案不同但相似的候选项。
 /// 注意：由于每一个候选项和正确答案只有一个字符之差，通常个数是很有限的。
 fn generate_different_texts(count: usize, right_text: &str) -> Vec<String> {
@@ -0,0 +1,263 @@
+//! 剪裁窗口的选择：居中、信息熵最大或显著性最高，以及清单中声明的焦点。
+
+use super::{Cropped, calculate_center_crop_coordinates};
+use crate::{errors::Result, fail, provider::manifest::FocalPoint};
+use magick_rust::{FilterType, MagickWand};
+use serde::Deserialize;
+
+// 分析时使用的缩略图宽度
+const ANALYSIS_WIDTH: usize = 64;
+// 沿可移动方向尝试的窗口位置数
+const CANDIDATE_STEPS: usize = 16;
+// 信息熵的直方图区间数
+const HISTOGRAM_BINS: usize = 32;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CropMode {
+    // 居中
+    #[default]
+    Center,
+    // 保留信息熵最大（细节最多）的区域
+    Entropy,
+    // 保留显著性（边缘和饱和度）最高的区域
+    Attention,
+}
+
+/// 合并剪裁模式与旧的 `centered_crop` 选项，返回 `None` 时不剪裁。
+pub fn resolve_mode(crop_mode: Option<CropMode>, centered_crop: Option<bool>) -> Option<CropMode> {
+    crop_mode.or(centered_crop.unwrap_or(false).then_some(CropMode::Center))
+}
+
+/// 计算目标宽高比的剪裁窗口，声明了焦点时以焦点为中心。
+pub fn calculate_crop_coordinates(
+    wand: &MagickWand,
+    target_width: usize,
+    target_height: usize,
+    mode: CropMode,
+    focal_point: Option<FocalPoint>,
+) -> Result<Cropped> {
+    let width = wand.get_image_width();
+    let height = wand.get_image_height();
+    let centered = calculate_center_crop_coordinates(width, height, target_width, target_height);
+    if let Some(focal_point) = focal_point {
+        return Ok(place_at(
+            centered,
+            width,
+            height,
+            focal_point.x * width as f64,
+            focal_point.y * height as f64,
+        ));
+    }
+    if mode == CropMode::Center || (centered.width == width && centered.height == height) {
+        return Ok(centered);
+    }
+
+    // 在缩小的副本上分析
+    let scale = (ANALYSIS_WIDTH as f64 / width as f64).min(1.0);
+    let small_width = ((width as f64 * scale).round() as usize).max(1);
+    let small_height = ((height as f64 * scale).round() as usize).max(1);
+    let small = wand.clone();
+    small.resize_image(small_width, small_height, FilterType::Triangle)?;
+    let pixels = small
+        .export_image_pixels(0, 0, small_width, small_height, "RGB")
+        .ok_or(fail!("failed to export image pixels"))?;
+    let window = (
+        ((centered.width as f64 * scale).round() as usize).clamp(1, small_width),
+        ((centered.height as f64 * scale).round() as usize).clamp(1, small_height),
+    );
+    let (x, y) = best_window(&pixels, small_width, small_height, window, mode);
+
+    Ok(place_at(
+        centered,
+        width,
+        height,
+        (x as f64 + window.0 as f64 / 2.0) / scale,
+        (y as f64 + window.1 as f64 / 2.0) / scale,
+    ))
+}
+
+// 将窗口的中心移动到指定位置（不超出图片边界）
+fn place_at(
+    cropped: Cropped,
+    width: usize,
+    height: usize,
+    center_x: f64,
+    center_y: f64,
+) -> Cropped {
+    let max_x = (width - cropped.width) as f64;
+    let max_y = (height - cropped.height) as f64;
+    let x = (center_x - cropped.width as f64 / 2.0).clamp(0.0, max_x);
+    let y = (center_y - cropped.height as f64 / 2.0).clamp(0.0, max_y);
+
+    Cropped {
+        x: x.round() as isize,
+        y: y.round() as isize,
+        ..cropped
+    }
+}
+
+// 在 RGB 像素上寻找得分最高的窗口位置，得分相同时靠近中心的优先
+fn best_window(
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+    window: (usize, usize),
+    mode: CropMode,
+) -> (usize, usize) {
+    let saliency = if mode == CropMode::Attention {
+        calculate_saliency(pixels, width, height)
+    } else {
+        vec![]
+    };
+    let candidates = |size: usize, window: usize| {
+        let max = size - window;
+        let steps = CANDIDATE_STEPS.min(max);
+        let mut offsets = (0..=steps)
+            .map(|i| if steps == 0 { 0 } else { i * max / steps })
+            .collect::<Vec<_>>();
+        offsets.sort_by_key(|offset| offset.abs_diff(max / 2));
+
+        offsets
+    };
+
+    let mut best = ((width - window.0) / 2, (height - window.1) / 2);
+    let mut best_score = f64::MIN;
+    for y in candidates(height, window.1) {
+        for x in candidates(width, window.0) {
+            let score = match mode {
+                CropMode::Entropy => window_entropy(pixels, width, (x, y), window),
+                _ => window_sum(&saliency, width, (x, y), window),
+            };
+            if score > best_score {
+                best_score = score;
+                best = (x, y);
+            }
+        }
+    }
+
+    best
+}
+
+fn luminance(pixels: &[u8], index: usize) -> f64 {
+    let (r, g, b) = (
+        pixels[index * 3] as f64,
+        pixels[index * 3 + 1] as f64,
+        pixels[index * 3 + 2] as f64,
+    );
+
+    0.299 * r + 0.587 * g + 0.114 * b
+}
+
+fn window_entropy(
+    pixels: &[u8],
+    width: usize,
+    (x, y): (usize, usize),
+    window: (usize, usize),
+) -> f64 {
+    let mut histogram = [0usize; HISTOGRAM_BINS];
+    for row in y..y + window.1 {
+        for col in x..x + window.0 {
+            let bin = luminance(pixels, row * width + col) as usize * HISTOGRAM_BINS / 256;
+            histogram[bin.min(HISTOGRAM_BINS - 1)] += 1;
+        }
+    }
+    let total = (window.0 * window.1) as f64;
+
+    histogram
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+// 每个像素的显著性：亮度梯度加上饱和度
+fn calculate_saliency(pixels: &[u8], width: usize, height: usize) -> Vec<f64> {
+    let mut saliency = vec![0.0; width * height];
+    for row in 0..height {
+        for col in 0..width {
+            let index = row * width + col;
+            let l = luminance(pixels, index);
+            let dx = if col + 1 < width {
+                (luminance(pixels, index + 1) - l).abs()
+            } else {
+                0.0
+            };
+            let dy = if row + 1 < height {
+                (luminance(pixels, index + width) - l).abs()
+            } else {
+                0.0
+            };
+            let rgb = &pixels[index * 3..index * 3 + 3];
+            let saturation =
+                (*rgb.iter().max().unwrap_or(&0) - *rgb.iter().min().unwrap_or(&0)) as f64;
+            saliency[index] = dx + dy + saturation * 0.5;
+        }
+    }
+
+    saliency
+}
+
+fn window_sum(values: &[f64], width: usize, (x, y): (usize, usize), window: (usize, usize)) -> f64 {
+    (y..y + window.1)
+        .map(|row| {
+            values[row * width + x..row * width + x + window.0]
+                .iter()
+                .sum::<f64>()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 左侧为纯灰色，右侧为彩色渐变
+    fn half_detailed(width: usize, height: usize) -> Vec<u8> {
+        let mut pixels = vec![];
+        for _ in 0..height {
+            for col in 0..width {
+                if col < width / 2 {
+                    pixels.extend([128, 128, 128]);
+                } else {
+                    let value = (col * 23 % 256) as u8;
+                    pixels.extend([value, 255 - value, 0]);
+                }
+            }
+        }
+
+        pixels
+    }
+
+    #[test]
+    fn test_best_window() {
+        let pixels = half_detailed(40, 10);
+
+        assert!(best_window(&pixels, 40, 10, (10, 10), CropMode::Entropy).0 >= 20);
+        assert!(best_window(&pixels, 40, 10, (10, 10), CropMode::Attention).0 >= 20);
+        // 没有细节时保持居中
+        let flat = vec![128; 40 * 10 * 3];
+        assert_eq!(
+            best_window(&flat, 40, 10, (10, 10), CropMode::Entropy),
+            (15, 0)
+        );
+    }
+
+    #[test]
+    fn test_place_at() {
+        let cropped = Cropped {
+            x: 50,
+            y: 0,
+            width: 100,
+            height: 100,
+        };
+
+        assert_eq!(place_at(cropped.clone(), 200, 100, 60.0, 50.0).x, 10);
+        // 不超出边界
+        assert_eq!(place_at(cropped.clone(), 200, 100, 0.0, 50.0).x, 0);
+        assert_eq!(place_at(cropped, 200, 100, 200.0, 50.0).x, 100);
+    }
+}
@@ -0,0 +1,263 @@
+//! 声明式的对抗性扭曲流水线，用于网格单元格和图片输出。
+//!
+//! 可以按请求提供效果列表，也可以使用内置的预设。每个效果的强度在给定范围内随机取值，
+//! 并被限制在该效果允许的范围内。
+
+use crate::{err, errors::Result};
+use magick_rust::{
+    DrawingWand, MagickEvaluateOperator, MagickWand, PixelInterpolateMethod, PixelWand,
+    bindings::{self, DrawLine, MagickSwirlImage, MagickWaveImage},
+};
+use rand::Rng;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Distortion {
+    Preset(Preset),
+    Effects(Vec<Effect>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    Light,
+    Medium,
+    Heavy,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Effect {
+    #[serde(rename = "type")]
+    pub kind: EffectKind,
+    // 强度范围 `[最小值, 最大值]`，含义见 `EffectKind`
+    pub strength: Option<[f64; 2]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectKind {
+    // 漩涡（角度）
+    Swirl,
+    // 波浪（振幅，单位为像素）
+    Wave,
+    // 高斯模糊（sigma）
+    Blur,
+    // JPEG 压缩伪影（压缩质量，越小越强）
+    JpegArtifacts,
+    // 亮度、饱和度和色相抖动（百分比）
+    ColorJitter,
+    // 水平翻转（概率）
+    Flip,
+    // 遮挡线（条数）
+    Lines,
+    // 高斯噪声（衰减系数）
+    Noise,
+}
+
+impl EffectKind {
+    // 默认的强度范围
+    fn default_strength(&self) -> [f64; 2] {
+        match self {
+            EffectKind::Swirl => [20.0, 60.0],
+            EffectKind::Wave => [1.0, 4.0],
+            EffectKind::Blur => [0.5, 1.5],
+            EffectKind::JpegArtifacts => [10.0, 30.0],
+            EffectKind::ColorJitter => [5.0, 15.0],
+            EffectKind::Flip => [0.5, 0.5],
+            EffectKind::Lines => [1.0, 3.0],
+            EffectKind::Noise => [0.5, 1.5],
+        }
+    }
+
+    // 允许的强度范围
+    fn limits(&self) -> [f64; 2] {
+        match self {
+            EffectKind::Swirl => [0.0, 360.0],
+            EffectKind::Wave => [0.0, 20.0],
+            EffectKind::Blur => [0.0, 8.0],
+            EffectKind::JpegArtifacts => [1.0, 100.0],
+            EffectKind::ColorJitter => [0.0, 50.0],
+            EffectKind::Flip => [0.0, 1.0],
+            EffectKind::Lines => [0.0, 20.0],
+            EffectKind::Noise => [0.0, 5.0],
+        }
+    }
+}
+
+impl Preset {
+    fn effects(&self) -> Vec<Effect> {
+        let kinds: &[EffectKind] = match self {
+            Preset::Light => &[EffectKind::ColorJitter, EffectKind::Noise],
+            Preset::Medium => &[
+                EffectKind::ColorJitter,
+                EffectKind::Flip,
+                EffectKind::Wave,
+                EffectKind::Lines,
+                EffectKind::Noise,
+            ],
+            Preset::Heavy => &[
+                EffectKind::ColorJitter,
+                EffectKind::Flip,
+                EffectKind::Swirl,
+                EffectKind::Wave,
+                EffectKind::Lines,
+                EffectKind::Blur,
+                EffectKind::JpegArtifacts,
+                EffectKind::Noise,
+            ],
+        };
+
+        kinds
+            .iter()
+            .map(|kind| Effect {
+                kind: *kind,
+                strength: None,
+            })
+            .collect()
+    }
+}
+
+impl Distortion {
+    fn effects(&self) -> Vec<Effect> {
+        match self {
+            Distortion::Preset(preset) => preset.effects(),
+            Distortion::Effects(effects) => effects.clone(),
+        }
+    }
+}
+
+pub fn apply(wand: &mut MagickWand, distortion: &Distortion) -> Result<()> {
+    let mut rng = rand::rng();
+    for effect in distortion.effects() {
+        let strength = pick_strength(&mut rng, &effect);
+        apply_effect(wand, effect.kind, strength, &mut rng)?;
+    }
+
+    Ok(())
+}
+
+// 在强度范围内随机取值（范围会被限制在效果允许的范围内）
+fn pick_strength(rng: &mut impl Rng, effect: &Effect) -> f64 {
+    let [low, high] = effect.kind.limits();
+    let [a, b] = effect
+        .strength
+        .unwrap_or(effect.kind.default_strength())
+        .map(|v| if v.is_nan() { low } else { v.clamp(low, high) });
+    let (min, max) = if a <= b { (a, b) } else { (b, a) };
+
+    rng.random_range(min..=max)
+}
+
+fn apply_effect(
+    wand: &mut MagickWand,
+    kind: EffectKind,
+    strength: f64,
+    rng: &mut impl Rng,
+) -> Result<()> {
+    let width = wand.get_image_width() as f64;
+    let height = wand.get_image_height() as f64;
+    match kind {
+        EffectKind::Swirl => {
+            let method = PixelInterpolateMethod::Undefined.into();
+            if unsafe { MagickSwirlImage(wand.wand, strength, method) }
+                != bindings::MagickBooleanType_MagickTrue
+            {
+                return err!("failed to swirl image");
+            }
+        }
+        EffectKind::Wave => {
+            let method = PixelInterpolateMethod::Undefined.into();
+            // 波长取图片宽度的 1/4 到 1/2
+            let wave_length = width / rng.random_range(2.0..=4.0);
+            if unsafe { MagickWaveImage(wand.wand, strength, wave_length, method) }
+                != bindings::MagickBooleanType_MagickTrue
+            {
+                return err!("failed to wave image");
+            }
+            // 波浪会增加图片高度，裁回原尺寸
+            let extra = wand.get_image_height().saturating_sub(height as usize);
+            wand.crop_image(width as usize, height as usize, 0, (extra / 2) as isize)?;
+            wand.reset_image_page("")?;
+        }
+        EffectKind::Blur => wand.gaussian_blur_image(0.0, strength)?,
+        EffectKind::JpegArtifacts => {
+            // 以低质量编码为 JPEG 后再读回
+            wand.set_image_compression_quality(strength.round() as usize)?;
+            let blob = wand.write_image_blob("jpeg")?;
+            let degraded = MagickWand::new();
+            degraded.read_image_blob(blob)?;
+            *wand = degraded;
+        }
+        EffectKind::ColorJitter => wand.modulate_image(
+            100.0 + rng.random_range(-strength..=strength),
+            100.0 + rng.random_range(-strength..=strength),
+            100.0 + rng.random_range(-strength..=strength) / 2.0,
+        )?,
+        EffectKind::Flip => {
+            if rng.random_bool(strength) {
+                wand.flop_image()?;
+            }
+        }
+        EffectKind::Lines => {
+            let mut draw = DrawingWand::new();
+            let mut stroke = PixelWand::new();
+            let gray = rng.random_range(0..=255);
+            stroke.set_color(&format!("rgb({gray},{gray},{gray})"))?;
+            draw.set_stroke_color(&stroke);
+            draw.set_stroke_width(rng.random_range(1.0..=2.0));
+            for _ in 0..strength.round() as usize {
+                // 从左边缘到右边缘的随机直线
+                let (start_y, end_y) = (
+                    rng.random_range(0.0..=height),
+                    rng.random_range(0.0..=height),
+                );
+                unsafe { DrawLine(draw.wand, 0.0, start_y, width, end_y) };
+            }
+            wand.draw_image(&draw)?;
+        }
+        EffectKind::Noise => {
+            wand.evaluate_image(MagickEvaluateOperator::GaussianNoise, strength)?
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize() {
+        let distortion: Distortion = serde_json::from_str(r#""medium""#).unwrap();
+        assert!(matches!(distortion, Distortion::Preset(Preset::Medium)));
+        assert_eq!(distortion.effects().len(), 5);
+
+        let distortion: Distortion =
+            serde_json::from_str(r#"[{"type": "swirl", "strength": [30, 40]}, {"type": "flip"}]"#)
+                .unwrap();
+        let effects = distortion.effects();
+        assert_eq!(effects[0].kind, EffectKind::Swirl);
+        assert_eq!(effects[0].strength, Some([30.0, 40.0]));
+        assert_eq!(effects[1].strength, None);
+    }
+
+    #[test]
+    fn test_pick_strength() {
+        let mut rng = rand::rng();
+        let effect = |kind, strength| Effect { kind, strength };
+
+        let strength = pick_strength(&mut rng, &effect(EffectKind::Swirl, Some([30.0, 40.0])));
+        assert!((30.0..=40.0).contains(&strength));
+        // 反向的范围
+        let strength = pick_strength(&mut rng, &effect(EffectKind::Blur, Some([1.0, 0.5])));
+        assert!((0.5..=1.0).contains(&strength));
+        // 超出允许范围
+        let strength = pick_strength(&mut rng, &effect(EffectKind::Flip, Some([2.0, 3.0])));
+        assert_eq!(strength, 1.0);
+        // 默认范围
+        let strength = pick_strength(&mut rng, &effect(EffectKind::Lines, None));
+        assert!((1.0..=3.0).contains(&strength));
+    }
+}
@@ -1,13 +1,19 @@
-use super::namegen;
 use crate::{
-    captchas::{Created, calculate_center_crop_coordinates, check_out_base},
+    captchas::{
+        Created, banner, check_out_base, crop, distortion, label,
+        output::{self, OutputControl},
+        perturb,
+    },
     err,
     errors::{Error, Result},
     fail,
-    models::{params::ChoicesControl, payload::SpecialPayload},
-    provider::{images_get, manifest::I18nName, random_right_with_wrongs},
+    models::{
+        params::{AlbumFilter, ChoicesControl},
+        payload::SpecialPayload,
+    },
+    provider::{Collection, manifest::I18nName},
 };
-use magick_rust::{CompositeOperator, DrawingWand, MagickWand, PixelWand, magick_wand_genesis};
+use magick_rust::{CompositeOperator, MagickWand, PixelWand, magick_wand_genesis};
 use rand::seq::{IndexedRandom, SliceRandom};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashSet, path::PathBuf, sync::Once};
@@ -17,6 +23,12 @@ const FALLBACK_RIGHT_COUNT: usize = 3;
 const FALLBACK_WITH_CHOICES: bool = false;
 const FALLBACK_CHOICES_COUNT: usize = 5;
 const FALLBACK_UNORDERED_RIGHT_PARTS: bool = false;
+const FALLBACK_PERTURB: bool = true;
+const FALLBACK_HARDNESS: f64 = 0.0;
+const MIN_LAYOUT_SIDE: usize = 2;
+const MAX_LAYOUT_SIDE: usize = 5;
+const ESCALATED_MAX_LAYOUT_SIDE: usize = 4;
+const ESCALATED_MAX_CHOICES_COUNT: usize = 8;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Params {
@@ -26,8 +38,10 @@ pub struct Params {
     pub cell_width: usize,
     // 单元格高度
     pub cell_height: usize,
-    // 居中剪裁
+    // 居中剪裁（已被 crop_mode 取代，等同于 center 模式）
     pub centered_crop: Option<bool>,
+    // 剪裁模式
+    pub crop_mode: Option<crop::CropMode>,
     // 水印字体家族
     pub watermark_font_family: String,
     // 水印字体大小
@@ -40,6 +54,32 @@ pub struct Params {
     pub unordered_right_parts: Option<bool>,
     // 验证控制
     pub verification_control: Option<VerifyControl>,
+    // 提示文本横幅（默认使用水印字体家族，仅支持合成输出）
+    pub banner: Option<banner::Banner>,
+    // 输出模式
+    pub output_mode: Option<OutputMode>,
+    // 随机扰动（默认开启）
+    pub perturb: Option<bool>,
+    // 扭曲效果（预设名称或效果列表），在绘制水印前应用于每个单元格
+    pub distortion: Option<distortion::Distortion>,
+    // 从相似图集中选取错误单元格的比例（0.0 - 1.0，默认不优先选取）
+    pub hardness: Option<f64>,
+    // 单元格标签（水印）的编号方案和样式
+    pub labels: Option<label::Labels>,
+    // 单元格间距（仅用于合成输出）
+    pub spacing: Option<usize>,
+    // 间距的边框颜色
+    pub border_color: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    // 合成为一张图片
+    #[default]
+    Composite,
+    // 每个单元格输出为单独的图片
+    Cells,
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
@@ -52,32 +92,54 @@ pub struct Payload {
     pub parts: Vec<usize>,
     pub subject: I18nName,
     pub choices: Vec<Vec<usize>>,
+    // 按单元格顺序排列的标签，`parts` 和 `choices` 中的编号 n 对应第 n 个标签
+    pub labels: Vec<String>,
+    // 逐单元格输出的文件
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cells: Option<Cells>,
     #[serde(skip_serializing)]
     pub images: Vec<PathBuf>,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct Cells {
+    // 布局
+    pub layout: String,
+    // 文件名，按单元格编号排列
+    pub file_names: Vec<String>,
+}
+
 static MAGICK_START: Once = Once::new();
 
 pub fn create(
     out_base: &str,
     choices_control: &ChoicesControl,
+    output: &OutputControl,
+    collection: &Collection,
+    album_filter: &AlbumFilter,
     params: &Params,
 ) -> Result<Created> {
+    // 单元格输出没有可以绘制横幅的画布
+    if params.banner.is_some() && params.output_mode == Some(OutputMode::Cells) {
+        return Err(Error::InvalidOutput(
+            "banner is not supported in the cells output mode".to_string(),
+        ));
+    }
+    if let Some(banner) = &params.banner {
+        banner.check()?;
+    }
     check_out_base(out_base)?;
     MAGICK_START.call_once(magick_wand_genesis);
-    let payload = make(
-        &params.layout,
-        params.right_count.unwrap_or(FALLBACK_RIGHT_COUNT),
-        choices_control
-            .with_choices
-            .unwrap_or(FALLBACK_WITH_CHOICES),
-        choices_control
-            .choices_count
-            .unwrap_or(FALLBACK_CHOICES_COUNT),
-        params
-            .unordered_right_parts
-            .unwrap_or(FALLBACK_UNORDERED_RIGHT_PARTS),
-    )?;
+    let labels = params.labels.clone().unwrap_or_default();
+    let choices_count = choices_control
+        .with_choices
+        .unwrap_or(FALLBACK_WITH_CHOICES)
+        .then(|| {
+            choices_control
+                .choices_count
+                .unwrap_or(FALLBACK_CHOICES_COUNT)
+        });
+    let payload = make(params, choices_count, collection, album_filter)?;
     let watermark_font_size = params
         .watermark_font_size
         .unwrap_or(calculate_watermark_font_size(
@@ -88,24 +150,23 @@ pub fn create(
         .watermark_font_weight
         .unwrap_or(FALLBACK_WATERMARK_FONT_WEIGHT);
 
-    let mut wand = MagickWand::new();
-    wand.new_image(
-        params.cell_width * 3,
-        params.cell_height * 3,
-        &PixelWand::new(),
-    )?;
+    let (columns, rows) = parse_layout(&params.layout)?;
     let mut wands = vec![];
     for (i, image) in payload.images.iter().enumerate() {
         let mut wand = MagickWand::new();
         wand.read_image(image.to_str().ok_or(fail!("bad image path"))?)?;
-        if params.centered_crop.unwrap_or(false) {
-            // 居中剪裁
-            let cropped = calculate_center_crop_coordinates(
-                wand.get_image_width(),
-                wand.get_image_height(),
+        if params.perturb.unwrap_or(FALLBACK_PERTURB) {
+            perturb::apply(&mut wand)?;
+        }
+        if let Some(mode) = crop::resolve_mode(params.crop_mode, params.centered_crop) {
+            // 按模式剪裁（清单声明了焦点时以焦点为中心）
+            let cropped = crop::calculate_crop_coordinates(
+                &wand,
                 params.cell_width,
                 params.cell_height,
-            );
+                mode,
+                collection.focal_point(image),
+            )?;
             wand.crop_image(
                 cropped.width,
                 cropped.height,
@@ -120,45 +181,21 @@ pub fn create(
             params.cell_height,
             magick_rust::FilterType::Triangle,
         )?;
-        let mut draw = DrawingWand::new();
-        let mut fill = PixelWand::new();
-        let mut border = PixelWand::new();
-        // 设置水印颜色和透明度
-        fill.set_color("white")?;
-        fill.set_alpha(0.45);
-        // 设置水印边框颜色
-        border.set_color("black")?;
-        // 设置水印的字体家族、大小、粗细、颜色
-        draw.set_font_family(&params.watermark_font_family)?;
-        draw.set_font_size(watermark_font_size);
-        draw.set_font_weight(watermark_font_weight);
-        // 设置字体为斜体
-        draw.set_font_style(magick_rust::StyleType::Italic);
-        draw.set_fill_color(&fill);
-        // 设置水印的边框颜色和宽度
-        draw.set_stroke_color(&border);
-        draw.set_stroke_width(1.0);
-        // 绘制水印和位置
-        draw.draw_annotation(1.0, watermark_font_size, &(i + 1).to_string())?;
-        wand.draw_image(&draw)?;
+        if let Some(distortion) = &params.distortion {
+            distortion::apply(&mut wand, distortion)?;
+        }
+        // 绘制标签水印
+        label::draw(
+            &mut wand,
+            &labels,
+            &payload.labels[i],
+            &params.watermark_font_family,
+            watermark_font_size,
+            watermark_font_weight,
+        )?;
         wands.push(wand);
     }
 
-    wand.set_format("jpg")?;
-
-    for (i, photo_wand) in wands.iter().enumerate() {
-        // todo: 此处未来将根据布局动态计算
-        let x = ((i % 3) * params.cell_width) as isize;
-        let y = ((i / 3) * params.cell_height) as isize;
-
-        wand.compose_images(photo_wand, CompositeOperator::Over, true, x, y)?;
-    }
-
-    let file_name = format!("{}.jpg", namegen());
-    let out_file = PathBuf::from(out_base).join(&file_name);
-
-    wand.write_image(out_file.to_str().ok_or(fail!("bad out file path"))?)?;
-
     // 找出正确答案索引
     let mut right_index = 0;
     for (i, choice) in payload.choices.iter().enumerate() {
@@ -167,6 +204,55 @@ pub fn create(
             break;
         }
     }
+    let mut payload = payload;
+
+    let file_name = match params.output_mode.unwrap_or_default() {
+        OutputMode::Composite => {
+            // 单元格之间及四周留出间距，以边框颜色填充
+            let spacing = params.spacing.unwrap_or(0);
+            let mut border = PixelWand::new();
+            if let Some(border_color) = &params.border_color {
+                border.set_color(border_color)?;
+            }
+            let mut wand = MagickWand::new();
+            wand.new_image(
+                params.cell_width * columns + spacing * (columns + 1),
+                params.cell_height * rows + spacing * (rows + 1),
+                &border,
+            )?;
+            for (i, photo_wand) in wands.iter().enumerate() {
+                let x = ((i % columns) * (params.cell_width + spacing) + spacing) as isize;
+                let y = ((i / columns) * (params.cell_height + spacing) + spacing) as isize;
+
+                wand.compose_images(photo_wand, CompositeOperator::Over, true, x, y)?;
+            }
+
+            if let Some(banner) = &params.banner {
+                let payload = SpecialPayload::Grid(payload.clone());
+                banner::draw(
+                    &mut wand,
+                    banner,
+                    &payload,
+                    Some(&collection.manifest),
+                    Some(&params.watermark_font_family),
+                )?;
+            }
+
+            Some(output::write(&mut wand, out_base, output)?)
+        }
+        OutputMode::Cells => {
+            let mut file_names = vec![];
+            for mut cell_wand in wands {
+                file_names.push(output::write(&mut cell_wand, out_base, output)?);
+            }
+            payload.cells = Some(Cells {
+                layout: format!("{columns}x{rows}"),
+                file_names,
+            });
+
+            None
+        }
+    };
 
     Ok(Created {
         file_name,
@@ -175,52 +261,83 @@ pub fn create(
     })
 }
 
+// `choices_count` 为候选项个数，不生成候选项时为 `None`
 fn make(
-    layout: &str,
-    right_count: usize,
-    with_choices: bool,
-    choices_count: usize,
-    unordered_right_parts: bool,
+    params: &Params,
+    choices_count: Option<usize>,
+    collection: &Collection,
+    album_filter: &AlbumFilter,
 ) -> Result<Payload> {
     let mut rng = rand::rng();
-    let images_count = layout_to_count(layout)?;
-    let (right, wrongs) = random_right_with_wrongs(right_count, images_count)?;
-    let right_images = images_get(&right.id).ok_or(fail!("the correct album was not found"))?;
-
-    let right_images = right_images
+    let right_count = params.right_count.unwrap_or(FALLBACK_RIGHT_COUNT);
+    let hardness = params.hardness.unwrap_or(FALLBACK_HARDNESS);
+    let (columns, rows) = parse_layout(&params.layout)?;
+    let images_count = columns * rows;
+    if right_count >= images_count {
+        return err!("the right count must be less than the number of cells");
+    }
+    let wrong_count = images_count - right_count;
+    let (right, wrongs) = collection.random_right_with_at_most_wrongs(
+        right_count,
+        wrong_count,
+        hardness,
+        album_filter,
+    )?;
+    // 正确图片可以是任何带有正确标签的图片（不限于正确图集）
+    let right_images = collection
+        .images_tagged(&right.id, album_filter)
         .choose_multiple(&mut rng, right_count)
         .cloned()
         .collect::<Vec<_>>();
+    if right_images.len() < right_count {
+        return err!("not enough images tagged with the correct album");
+    }
 
+    // 轮流从错误图集中选取不带正确标签的图片（错误图集不足时，同一图集会被选取多次）
     let mut full = vec![];
-    for album in wrongs.iter() {
-        if full.len() >= images_count - right_count {
-            break;
-        }
-        if let Some(images) = images_get(&album.id)
-            && !images.is_empty()
-        {
-            if let Some(image) = images.choose(&mut rng).cloned() {
-                full.push(image);
+    while full.len() < wrong_count {
+        let picked_count = full.len();
+        for album in wrongs.iter() {
+            if full.len() >= wrong_count {
+                break;
+            }
+            if let Some(images) =
+                collection.images_get_without(&album.id, &[&right.id], album_filter)
+            {
+                let unused = images
+                    .iter()
+                    .filter(|image| !full.contains(*image))
+                    .collect::<Vec<_>>();
+                if let Some(image) = unused.choose(&mut rng) {
+                    full.push((*image).clone());
+                }
             }
         }
+        if full.len() == picked_count {
+            // 一轮下来没有新图片，说明错误图片已耗尽
+            return err!("not enough images to fill the grid");
+        }
     }
 
     full.append(&mut right_images.clone());
     full.shuffle(&mut rng);
 
+    // 根据标签计算正确的单元格
     let mut parts = vec![];
     for (i, image) in full.iter().enumerate() {
-        if right_images.contains(image) {
+        if collection.is_tagged(image, &right.id) {
             parts.push(i + 1);
         }
     }
 
-    if unordered_right_parts {
+    if params
+        .unordered_right_parts
+        .unwrap_or(FALLBACK_UNORDERED_RIGHT_PARTS)
+    {
         parts.shuffle(&mut rng);
     }
 
-    let choices: Vec<Vec<usize>> = if with_choices {
+    let choices: Vec<Vec<usize>> = if let Some(choices_count) = choices_count {
         // 将正确答案添加到选择中并打乱
         let mut choices = generate_different_parts(choices_count - 1, &parts, 1, images_count)?;
         choices.push(parts.clone());
@@ -235,15 +352,67 @@ fn make(
         parts,
         subject: right.name.clone(),
         choices,
+        labels: params
+            .labels
+            .as_ref()
+            .and_then(|labels| labels.scheme)
+            .unwrap_or_default()
+            .labels(images_count),
+        cells: None,
         images: full,
     })
 }
 
-fn layout_to_count(layout: &str) -> Result<usize> {
-    if layout == "3x3" {
-        Ok(9)
+/// 解析 `<列>x<行>` 格式的布局。
+fn parse_layout(layout: &str) -> Result<(usize, usize)> {
+    let invalid = || Error::InvalidGridLayout(layout.to_string());
+    let (columns, rows) = layout.split_once('x').ok_or_else(invalid)?;
+    let columns = columns.parse::<usize>().map_err(|_| invalid())?;
+    let rows = rows.parse::<usize>().map_err(|_| invalid())?;
+
+    if (MIN_LAYOUT_SIDE..=MAX_LAYOUT_SIDE).contains(&columns)
+        && (MIN_LAYOUT_SIDE..=MAX_LAYOUT_SIDE).contains(&rows)
+    {
+        Ok((columns, rows))
     } else {
-        Err(Error::InvalidGridLayout(layout.to_string()))
+        Err(invalid())
+    }
+}
+
+/// 布局的单元格总数（无效布局返回 `None`）。
+pub fn layout_cells(layout: &str) -> Option<usize> {
+    parse_layout(layout)
+        .ok()
+        .map(|(columns, rows)| columns * rows)
+}
+
+/// 单元格标签（无效布局返回 `None`）。
+pub fn cell_labels(params: &Params) -> Option<Vec<String>> {
+    let scheme = params
+        .labels
+        .as_ref()
+        .and_then(|labels| labels.scheme)
+        .unwrap_or_default();
+
+    layout_cells(&params.layout).map(|cells| scheme.labels(cells))
+}
+
+/// 根据用户近期的失败次数提升难度：更多候选项，失败较多时扩大网格。
+pub fn escalate(params: &mut Params, choices_control: &mut ChoicesControl, level: usize) {
+    if level == 0 {
+        return;
+    }
+    let choices_count = choices_control
+        .choices_count
+        .unwrap_or(FALLBACK_CHOICES_COUNT);
+    choices_control.choices_count =
+        Some((choices_count + level).min(ESCALATED_MAX_CHOICES_COUNT.max(choices_count)));
+    if level >= 2
+        && let Ok((columns, rows)) = parse_layout(&params.layout)
+    {
+        let columns = columns.max((columns + 1).min(ESCALATED_MAX_LAYOUT_SIDE));
+        let rows = rows.max((rows + 1).min(ESCALATED_MAX_LAYOUT_SIDE));
+        params.layout = format!("{columns}x{rows}");
     }
 }
 
@@ -300,40 +469,86 @@ fn calculate_watermark_font_size(width: usize, height: usize) -> f64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
 
-    fn setup() {
+    fn setup() -> Arc<Collection> {
         crate::provider::init();
-    }
 
-    #[test]
-    fn test_create() {
-        setup();
+        crate::provider::get_collection(None).unwrap()
+    }
 
-        let choices_control = ChoicesControl {
-            with_choices: Some(true),
-            choices_count: Some(4),
-        };
-        let params = Params {
-            layout: String::from("3x3"),
+    fn params(layout: &str) -> Params {
+        Params {
+            layout: String::from(layout),
             cell_width: 180,
             cell_height: 140,
             centered_crop: None,
+            crop_mode: None,
             watermark_font_family: String::from("Open Sans"),
             watermark_font_size: None,
             watermark_font_weight: Some(600),
             right_count: None,
             unordered_right_parts: None,
             verification_control: None,
+            banner: None,
+            output_mode: None,
+            perturb: None,
+            distortion: None,
+            hardness: None,
+            labels: None,
+            spacing: None,
+            border_color: None,
+        }
+    }
+
+    #[test]
+    fn test_create() {
+        let collection = setup();
+
+        let choices_control = ChoicesControl {
+            with_choices: Some(true),
+            choices_count: Some(4),
         };
 
-        let _ = create("namespace/out", &choices_control, &params).unwrap();
+        let _ = create(
+            "namespace/out",
+            &choices_control,
+            &OutputControl::default(),
+            &collection,
+            &AlbumFilter::default(),
+            &params("3x3"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_create_cells_with_banner() {
+        let collection = setup();
+
+        let mut cells = params("3x3");
+        cells.output_mode = Some(OutputMode::Cells);
+        cells.banner = Some(serde_json::from_str("{}").unwrap());
+        let result = create(
+            "namespace/out",
+            &ChoicesControl::default(),
+            &OutputControl::default(),
+            &collection,
+            &AlbumFilter::default(),
+            &cells,
+        );
+        assert!(matches!(result, Err(Error::InvalidOutput(_))));
     }
 
     #[test]
     fn test_make() {
-        setup();
+        let collection = setup();
 
-        let payload = make("3x3", 3, true, 4, false).unwrap();
+        let mut lettered = params("3x3");
+        lettered.labels = Some(label::Labels {
+            scheme: Some(label::Scheme::Letters),
+            ..Default::default()
+        });
+        let payload = make(&lettered, Some(4), &collection, &AlbumFilter::default()).unwrap();
 
         assert_eq!(payload.parts.len(), 3);
         // 测试 right_parts 是否有序
@@ -341,5 +556,23 @@ mod tests {
         assert_eq!(payload.images.len(), 9);
         assert_eq!(payload.choices.len(), 4);
         assert!(payload.choices.iter().all(|c| c.len() == 3));
+        assert_eq!(payload.labels[8], "I");
+
+        // 错误图集不足时，也能填满更大的网格
+        let mut larger = params("4x4");
+        larger.hardness = Some(1.0);
+        let payload = make(&larger, None, &collection, &AlbumFilter::default()).unwrap();
+        assert_eq!(payload.parts.len(), 3);
+        assert_eq!(payload.images.len(), 16);
+    }
+
+    #[test]
+    fn test_parse_layout() {
+        assert_eq!(parse_layout("3x3").unwrap(), (3, 3));
+        assert_eq!(parse_layout("4x3").unwrap(), (4, 3));
+        assert!(parse_layout("1x3").is_err());
+        assert!(parse_layout("6x6").is_err());
+        assert!(parse_layout("3*3").is_err());
+        assert!(parse_layout("x3").is_err());
     }
 }
@@ -1,13 +1,18 @@
-use super::namegen;
 use crate::{
-    captchas::{Created, calculate_center_crop_coordinates, check_out_base},
+    captchas::{
+        Created, banner, check_out_base, crop, distortion,
+        output::{self, OutputControl},
+        perturb,
+    },
     err,
     errors::Result,
     fail,
-    models::{params::ChoicesControl, payload::SpecialPayload},
-    provider::{images_get, manifest::I18nName, random_right_with_wrongs},
+    models::{
+        params::{AlbumFilter, ChoicesControl},
+        payload::SpecialPayload,
+    },
+    provider::{Collection, manifest::I18nName},
 };
-use log::debug;
 use magick_rust::{
     MagickWand,
     bindings::{
@@ -21,9 +26,11 @@ use rand::{
     seq::{IndexedRandom, SliceRandom},
 };
 use serde::{Deserialize, Serialize};
-use std::{path::PathBuf, sync::Once};
+use std::sync::Once;
 
 const FALLBACK_DYNAMIC_DIGEST: bool = false;
+const FALLBACK_PERTURB: bool = true;
+const FALLBACK_HARDNESS: f64 = 0.0;
 const FALLBACK_WITH_CHOICES: bool = true;
 const FALLBACK_CHOICES_COUNT: usize = 4;
 
@@ -33,10 +40,20 @@ pub struct Params {
     pub width: Option<usize>,
     // 图片高度
     pub height: Option<usize>,
-    // 居中剪裁
+    // 居中剪裁（已被 crop_mode 取代，等同于 center 模式）
     pub centered_crop: Option<bool>,
+    // 剪裁模式
+    pub crop_mode: Option<crop::CropMode>,
     // 动态摘要
     pub dynamic_digest: Option<bool>,
+    // 提示文本横幅
+    pub banner: Option<banner::Banner>,
+    // 随机扰动（默认开启）
+    pub perturb: Option<bool>,
+    // 扭曲效果（预设名称或效果列表）
+    pub distortion: Option<distortion::Distortion>,
+    // 从相似图集中选取错误候选项的比例（0.0 - 1.0，默认不优先选取）
+    pub hardness: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +62,9 @@ pub struct Payload {
     pub name: I18nName,
     // 候选项
     pub choices: Vec<I18nName>,
+    // 正确答案的别名（仅用于验证，不返回给调用方）
+    #[serde(skip)]
+    pub aliases: Vec<String>,
 }
 
 static MAGICK_START: Once = Once::new();
@@ -52,8 +72,14 @@ static MAGICK_START: Once = Once::new();
 pub fn create(
     out_base: &str,
     choices_control: &ChoicesControl,
+    output: &OutputControl,
+    collection: &Collection,
+    album_filter: &AlbumFilter,
     params: &Params,
 ) -> Result<Created> {
+    if let Some(banner) = &params.banner {
+        banner.check()?;
+    }
     check_out_base(out_base)?;
     let with_choices = choices_control
         .with_choices
@@ -68,7 +94,16 @@ pub fn create(
     };
 
     // 获取随机图集
-    let (ref right, wrongs) = random_right_with_wrongs(1, choices_count)?;
+    let (ref right, wrongs) = collection.random_right_with_wrongs(
+        1,
+        choices_count,
+        params.hardness.unwrap_or(FALLBACK_HARDNESS),
+        album_filter,
+    )?;
+    let wrong_ids = wrongs
+        .iter()
+        .map(|album| album.id.clone())
+        .collect::<Vec<_>>();
     let mut full = [vec![right.clone()], wrongs].concat();
     // 随机化图集顺序
     full.shuffle(&mut rand::rng());
@@ -85,103 +120,120 @@ pub fn create(
     }
 
     // 选择正确的图片（从数组中随机选择一个）
-    let right_images = images_get(&right.id).ok_or(fail!("no album found: {}", right.id))?;
+    // 排除带有错误候选项标签的图片，避免出现多个正确答案
+    let wrong_ids = wrong_ids.iter().map(String::as_str).collect::<Vec<_>>();
+    let right_images = collection
+        .images_get_without(&right.id, &wrong_ids, album_filter)
+        .ok_or(fail!("no album found: {}", right.id))?;
     let right_image = right_images
         .choose(&mut rand::rng())
-        .ok_or(fail!("no images found for album {}", right.id))?;
-    // 生成新的文件名
-    let extension = right_image
-        .extension()
-        .and_then(|s| s.to_str())
-        .unwrap_or("jpg");
-    let file_name = format!("{}.{extension}", namegen());
-
+        .ok_or(fail!("no unambiguous images found for album {}", right.id))?;
     // 是否需要缩放
     let needs_resize = params.height.is_some() || params.width.is_some();
-    // 是否需要处理（需要缩放或重写摘要）
-    let needs_process = dynamic_digest || needs_resize;
-    if needs_process {
-        MAGICK_START.call_once(magick_wand_genesis);
-        let wand = MagickWand::new();
-        wand.read_image(right_image.to_str().ok_or(fail!("bad image path"))?)?;
-        if needs_resize {
-            // 获得缩放后的完整尺寸
-            let (width, height) = calculate_proportional_size(
-                wand.get_image_width(),
-                wand.get_image_height(),
-                params.width,
-                params.height,
+    let payload = SpecialPayload::Image(Payload {
+        name: right.name.clone(),
+        choices,
+        aliases: right.all_aliases(),
+    });
+    // 总是重新编码，不直接输出原始文件
+    MAGICK_START.call_once(magick_wand_genesis);
+    let mut wand = MagickWand::new();
+    wand.read_image(right_image.to_str().ok_or(fail!("bad image path"))?)?;
+    if params.perturb.unwrap_or(FALLBACK_PERTURB) {
+        perturb::apply(&mut wand)?;
+    }
+    if needs_resize {
+        // 获得缩放后的完整尺寸
+        let (width, height) = calculate_proportional_size(
+            wand.get_image_width(),
+            wand.get_image_height(),
+            params.width,
+            params.height,
+        )?;
+        if let Some(mode) = crop::resolve_mode(params.crop_mode, params.centered_crop) {
+            // 按模式剪裁（清单声明了焦点时以焦点为中心）
+            let cropped = crop::calculate_crop_coordinates(
+                &wand,
+                width,
+                height,
+                mode,
+                collection.focal_point(right_image),
+            )?;
+            wand.crop_image(
+                cropped.width,
+                cropped.height,
+                cropped.x as isize,
+                cropped.y as isize,
             )?;
-            if params.centered_crop.unwrap_or(false) {
-                // 居中剪裁
-                let cropped = calculate_center_crop_coordinates(
-                    wand.get_image_width(),
-                    wand.get_image_height(),
-                    width,
-                    height,
-                );
-                wand.crop_image(
-                    cropped.width,
-                    cropped.height,
-                    cropped.x as isize,
-                    cropped.y as isize,
-                )?;
-            }
-            // 缩放图像
-            wand.resize_image(width, height, magick_rust::FilterType::Triangle)?;
-        }
-        if dynamic_digest {
-            // 动态摘要（随机重写像素）
-            let width = wand.get_image_width();
-            let height = wand.get_image_height();
-            // 生成随机行和列
-            let mut rng = rng();
-            let rand_row = rng.random_range(1..=height);
-            let rand_col = rng.random_range(1..=width);
-            unsafe {
-                // 创建像素迭代器
-                let iterator_ptr = NewPixelIterator(wand.wand);
-                let row_width_ptr = &mut (1_usize) as *mut usize;
-                // 设置当前的像素行
-                PixelSetIteratorRow(iterator_ptr, (rand_row - 1) as isize);
-                // 获取当前行的像素列表
-                let pixels = std::slice::from_raw_parts_mut(
-                    PixelGetCurrentIteratorRow(iterator_ptr, row_width_ptr),
-                    rand_col,
-                );
-                // 设置列中的随机像素为黑色
-                PixelSetColor(
-                    pixels[rand_col - 1],
-                    c"#000000".as_ptr() as *const std::ffi::c_char,
-                );
-                // 同步像素迭代器
-                PixelSyncIterator(iterator_ptr);
-                // 销毁像素迭代器
-                DestroyPixelIterator(iterator_ptr);
-            };
         }
-        let out_file = PathBuf::from(out_base).join(&file_name);
-        wand.write_image(out_file.to_str().ok_or(fail!("bad out file path"))?)?;
-    } else {
-        // 如果不涉及任何图像处理，则直接复制文件
-        debug!(
-            "Copying image without processing: {}",
-            right_image.display()
-        );
-        let out_file = PathBuf::from(out_base).join(&file_name);
-        std::fs::copy(right_image, out_file)?;
+        // 缩放图像
+        wand.resize_image(width, height, magick_rust::FilterType::Triangle)?;
+    }
+    if dynamic_digest {
+        // 动态摘要（随机重写像素）
+        let width = wand.get_image_width();
+        let height = wand.get_image_height();
+        // 生成随机行和列
+        let mut rng = rng();
+        let rand_row = rng.random_range(1..=height);
+        let rand_col = rng.random_range(1..=width);
+        unsafe {
+            // 创建像素迭代器
+            let iterator_ptr = NewPixelIterator(wand.wand);
+            let row_width_ptr = &mut (1_usize) as *mut usize;
+            // 设置当前的像素行
+            PixelSetIteratorRow(iterator_ptr, (rand_row - 1) as isize);
+            // 获取当前行的像素列表
+            let pixels = std::slice::from_raw_parts_mut(
+                PixelGetCurrentIteratorRow(iterator_ptr, row_width_ptr),
+                rand_col,
+            );
+            // 设置列中的随机像素为黑色
+            PixelSetColor(
+                pixels[rand_col - 1],
+                c"#000000".as_ptr() as *const std::ffi::c_char,
+            );
+            // 同步像素迭代器
+            PixelSyncIterator(iterator_ptr);
+            // 销毁像素迭代器
+            DestroyPixelIterator(iterator_ptr);
+        };
+    }
+    if let Some(distortion) = &params.distortion {
+        distortion::apply(&mut wand, distortion)?;
+    }
+    if let Some(banner) = &params.banner {
+        banner::draw(
+            &mut wand,
+            banner,
+            &payload,
+            Some(&collection.manifest),
+            None,
+        )?;
     }
 
+    let file_name = output::write(&mut wand, out_base, output)?;
+
     Ok(Created {
-        file_name,
+        file_name: Some(file_name),
         right_index,
-        payload: SpecialPayload::Image(Payload {
-            name: right.name.clone(),
-            choices,
-        }),
+        payload,
     })
 }
 
+/// 根据用户近期的失败次数提升难度：更多的候选项。
+/// 提升后不超过 `max_choices`（可用图集能提供的候选项个数），但不会减少已指定的个数。
+pub fn escalate(choices_control: &mut ChoicesControl, level: usize, max_choices: usize) {
+    if level == 0 {
+        return;
+    }
+    let choices_count = choices_control
+        .choices_count
+        .unwrap_or(FALLBACK_CHOICES_COUNT);
+    choices_control.choices_count =
+        Some((choices_count + level).min(max_choices.max(choices_count)));
+}
+
 pub fn calculate_proportional_size(
     original_width: usize,
     original_height: usize,
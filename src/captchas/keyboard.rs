@@ -0,0 +1,176 @@
+//! 将候选项（或网格单元格）按聊天平台的限制排列成按钮键盘。
+
+use crate::{
+    errors::{Error, Result},
+    models::payload::SpecialPayload,
+    vars::LANG_FALLBACKS,
+};
+use serde::{Deserialize, Serialize};
+
+const FALLBACK_MAX_PER_ROW: usize = 5;
+const FALLBACK_MAX_ROWS: usize = 5;
+const FALLBACK_MAX_LABEL_LEN: usize = 32;
+const FALLBACK_LANG: &str = "en";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct KeyboardControl {
+    // 每行最多按钮数
+    pub max_per_row: Option<usize>,
+    // 最多行数
+    pub max_rows: Option<usize>,
+    // 按钮文本的最大长度（字符数），超出部分以省略号截断
+    pub max_label_len: Option<usize>,
+    // 按钮文本的语言（用于图片验证的候选项），默认为语言回退链中的第一个
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Keyboard {
+    // 按钮的来源，决定了回调索引的含义
+    pub source: Source,
+    pub rows: Vec<Vec<Button>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    // 回调索引为候选项索引，可直接用于 `index` 答案
+    Choices,
+    // 回调索引为单元格编号（从 1 开始），可用于 `grid` 答案的组成部分
+    Cells,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Button {
+    pub label: String,
+    pub index: usize,
+}
+
+/// 排列键盘，没有可用的按钮时返回 `None`。
+pub fn build(payload: &SpecialPayload, control: &KeyboardControl) -> Result<Option<Keyboard>> {
+    let lang = control
+        .lang
+        .as_deref()
+        .or(LANG_FALLBACKS.first().map(String::as_str))
+        .unwrap_or(FALLBACK_LANG);
+    let (source, buttons) = match payload {
+        SpecialPayload::Classic(payload) => (Source::Choices, payload.choices.clone()),
+        SpecialPayload::Image(payload) => (
+            Source::Choices,
+            payload
+                .choices
+                .iter()
+                .map(|name| name.resolve(lang).cloned().unwrap_or_default())
+                .collect(),
+        ),
+        SpecialPayload::Grid(payload) if payload.choices.is_empty() => {
+            (Source::Cells, payload.labels.clone())
+        }
+        SpecialPayload::Grid(payload) => (
+            Source::Choices,
+            payload
+                .choices
+                .iter()
+                .map(|parts| {
+                    parts
+                        .iter()
+                        .map(|part| payload.labels[part - 1].as_str())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })
+                .collect(),
+        ),
+    };
+    if buttons.is_empty() {
+        return Ok(None);
+    }
+
+    let max_label_len = control
+        .max_label_len
+        .unwrap_or(FALLBACK_MAX_LABEL_LEN)
+        .max(1);
+    let buttons = buttons
+        .into_iter()
+        .enumerate()
+        .map(|(i, label)| Button {
+            label: truncate(&label, max_label_len),
+            // 单元格编号从 1 开始
+            index: if source == Source::Cells { i + 1 } else { i },
+        })
+        .collect::<Vec<_>>();
+    let rows = arrange(
+        buttons,
+        control.max_per_row.unwrap_or(FALLBACK_MAX_PER_ROW),
+        control.max_rows.unwrap_or(FALLBACK_MAX_ROWS),
+    )?;
+
+    Ok(Some(Keyboard { source, rows }))
+}
+
+// 将按钮均匀地分配到尽可能少的行中
+fn arrange(buttons: Vec<Button>, max_per_row: usize, max_rows: usize) -> Result<Vec<Vec<Button>>> {
+    let capacity = max_per_row * max_rows;
+    if buttons.len() > capacity {
+        return Err(Error::KeyboardOverflow {
+            buttons: buttons.len(),
+            capacity,
+        });
+    }
+    let rows_count = buttons.len().div_ceil(max_per_row);
+    let per_row = buttons.len().div_ceil(rows_count);
+
+    Ok(buttons.chunks(per_row).map(|row| row.to_vec()).collect())
+}
+
+fn truncate(label: &str, max_len: usize) -> String {
+    if label.chars().count() <= max_len {
+        label.to_string()
+    } else {
+        let mut truncated = label.chars().take(max_len - 1).collect::<String>();
+        truncated.push('…');
+
+        truncated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buttons(count: usize) -> Vec<Button> {
+        (0..count)
+            .map(|i| Button {
+                label: i.to_string(),
+                index: i,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_arrange() {
+        let rows = arrange(buttons(5), 4, 5).unwrap();
+        assert_eq!(rows.iter().map(Vec::len).collect::<Vec<_>>(), vec![3, 2]);
+
+        let rows = arrange(buttons(9), 5, 5).unwrap();
+        assert_eq!(rows.iter().map(Vec::len).collect::<Vec<_>>(), vec![5, 4]);
+
+        let rows = arrange(buttons(4), 5, 1).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][3].index, 3);
+
+        assert!(matches!(
+            arrange(buttons(26), 5, 5),
+            Err(Error::KeyboardOverflow {
+                buttons: 26,
+                capacity: 25
+            })
+        ));
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("Cat", 5), "Cat");
+        assert_eq!(truncate("Airplane", 5), "Airp…");
+        assert_eq!(truncate("飞机模型", 3), "飞机…");
+    }
+}
@@ -0,0 +1,201 @@
+//! 网格单元格的编号标签：编号方案、位置和样式。
+
+use crate::errors::Result;
+use magick_rust::{AlignType, DrawingWand, MagickWand, PixelWand, bindings::DrawRoundRectangle};
+use serde::Deserialize;
+
+const FALLBACK_COLOR: &str = "white";
+const FALLBACK_STROKE_COLOR: &str = "black";
+const FALLBACK_OPACITY: f64 = 0.45;
+const FALLBACK_CHIP_OPACITY: f64 = 0.75;
+// 有背景色块时文字默认不透明
+const FALLBACK_CHIP_TEXT_OPACITY: f64 = 1.0;
+
+const CJK_DIGITS: [&str; 9] = ["一", "二", "三", "四", "五", "六", "七", "八", "九"];
+// 不会被渲染为 emoji 的符号
+const SYMBOLS: [&str; 25] = [
+    "●", "■", "▲", "◆", "★", "○", "□", "△", "◇", "☆", "▼", "▽", "◁", "▷", "◐", "◑", "◎", "✚", "✕",
+    "♤", "♧", "♡", "♢", "♪", "♫",
+];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Labels {
+    // 编号方案
+    pub scheme: Option<Scheme>,
+    // 位置
+    pub position: Option<Position>,
+    // 文字颜色
+    pub color: Option<String>,
+    // 文字描边颜色（`none` 表示不描边）
+    pub stroke_color: Option<String>,
+    // 文字不透明度
+    pub opacity: Option<f64>,
+    // 背景色块颜色，未指定时不绘制色块
+    pub background: Option<String>,
+    // 背景色块不透明度
+    pub background_opacity: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scheme {
+    // 1, 2, 3...
+    #[default]
+    Numbers,
+    // A, B, C...
+    Letters,
+    // 一, 二, 三...
+    Cjk,
+    // ●, ■, ▲...
+    Symbols,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Position {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+impl Scheme {
+    /// 单元格编号（从 1 开始）对应的标签，超出方案范围时使用数字。
+    pub fn label(self, n: usize) -> String {
+        match self {
+            Scheme::Numbers => None,
+            Scheme::Letters => (n <= 26).then(|| ((b'A' + (n - 1) as u8) as char).to_string()),
+            Scheme::Cjk => (n < 100).then(|| cjk_numeral(n)),
+            Scheme::Symbols => SYMBOLS.get(n - 1).map(|symbol| symbol.to_string()),
+        }
+        .unwrap_or_else(|| n.to_string())
+    }
+
+    /// 按单元格顺序排列的全部标签。
+    pub fn labels(self, cells: usize) -> Vec<String> {
+        (1..=cells).map(|n| self.label(n)).collect()
+    }
+}
+
+fn cjk_numeral(n: usize) -> String {
+    let (tens, ones) = (n / 10, n % 10);
+    let mut numeral = String::new();
+    if tens > 1 {
+        numeral.push_str(CJK_DIGITS[tens - 1]);
+    }
+    if tens > 0 {
+        numeral.push('十');
+    }
+    if ones > 0 {
+        numeral.push_str(CJK_DIGITS[ones - 1]);
+    }
+
+    numeral
+}
+
+/// 在单元格上绘制标签。
+pub fn draw(
+    wand: &mut MagickWand,
+    labels: &Labels,
+    text: &str,
+    font_family: &str,
+    font_size: f64,
+    font_weight: usize,
+) -> Result<()> {
+    let width = wand.get_image_width() as f64;
+    let height = wand.get_image_height() as f64;
+    let has_chip = labels.background.is_some();
+    // 估算文字尺寸（没有字体度量，按半角/全角宽度估算）
+    let text_width = text
+        .chars()
+        .map(|c| if c.is_ascii() { 0.6 } else { 1.0 })
+        .sum::<f64>()
+        * font_size;
+    let (padding, margin) = if has_chip {
+        (font_size * 0.2, font_size * 0.15)
+    } else {
+        (0.0, 1.0)
+    };
+    let box_width = text_width + padding * 2.0;
+    let box_height = font_size + padding * 2.0;
+    let (x, y) = match labels.position.unwrap_or_default() {
+        Position::TopLeft => (margin, margin),
+        Position::TopRight => (width - box_width - margin, margin),
+        Position::BottomLeft => (margin, height - box_height - margin),
+        Position::BottomRight => (width - box_width - margin, height - box_height - margin),
+        Position::Center => ((width - box_width) / 2.0, (height - box_height) / 2.0),
+    };
+
+    let mut draw = DrawingWand::new();
+    if let Some(background) = &labels.background {
+        // 绘制背景色块
+        let mut chip = PixelWand::new();
+        chip.set_color(background)?;
+        chip.set_alpha(labels.background_opacity.unwrap_or(FALLBACK_CHIP_OPACITY));
+        draw.set_fill_color(&chip);
+        let radius = box_height * 0.25;
+        unsafe {
+            DrawRoundRectangle(
+                draw.wand,
+                x,
+                y,
+                x + box_width,
+                y + box_height,
+                radius,
+                radius,
+            )
+        };
+    }
+    let mut fill = PixelWand::new();
+    // 设置标签颜色和透明度
+    fill.set_color(labels.color.as_deref().unwrap_or(FALLBACK_COLOR))?;
+    fill.set_alpha(labels.opacity.unwrap_or(if has_chip {
+        FALLBACK_CHIP_TEXT_OPACITY
+    } else {
+        FALLBACK_OPACITY
+    }));
+    // 设置标签的字体家族、大小、粗细
+    draw.set_font_family(font_family)?;
+    draw.set_font_size(font_size);
+    draw.set_font_weight(font_weight);
+    // 设置字体为斜体
+    draw.set_font_style(magick_rust::StyleType::Italic);
+    draw.set_fill_color(&fill);
+    // 设置标签的描边颜色和宽度
+    let stroke_color = labels
+        .stroke_color
+        .as_deref()
+        .unwrap_or(FALLBACK_STROKE_COLOR);
+    if stroke_color != "none" {
+        let mut stroke = PixelWand::new();
+        stroke.set_color(stroke_color)?;
+        draw.set_stroke_color(&stroke);
+        draw.set_stroke_width(1.0);
+    }
+    // 在区域内水平居中，基线位于区域底部的内边距之上
+    draw.set_text_alignment(AlignType::Center);
+    draw.draw_annotation(x + box_width / 2.0, y + padding + font_size * 0.9, text)?;
+    wand.draw_image(&draw)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels() {
+        assert_eq!(Scheme::Numbers.labels(4), vec!["1", "2", "3", "4"]);
+        assert_eq!(Scheme::Letters.labels(4), vec!["A", "B", "C", "D"]);
+        assert_eq!(Scheme::Symbols.label(25), "♫");
+        assert_eq!(Scheme::Symbols.label(26), "26");
+        assert_eq!(Scheme::Cjk.label(1), "一");
+        assert_eq!(Scheme::Cjk.label(10), "十");
+        assert_eq!(Scheme::Cjk.label(16), "十六");
+        assert_eq!(Scheme::Cjk.label(20), "二十");
+        assert_eq!(Scheme::Cjk.label(25), "二十五");
+    }
+}
@@ -1,15 +1,40 @@
 use crate::{errors::Result, fail, models::payload::SpecialPayload};
 
+pub mod banner;
 pub mod classic;
+pub mod crop;
+pub mod distortion;
 pub mod grid;
 pub mod image;
+pub mod keyboard;
+pub mod label;
+pub mod output;
+pub mod perturb;
+pub mod prompt;
 
 pub struct Created {
-    pub file_name: String,
+    // 输出文件名（网格的逐单元格输出模式下为空）
+    pub file_name: Option<String>,
     pub right_index: usize,
     pub payload: SpecialPayload,
 }
 
+impl Created {
+    // 所有输出文件，按顺序排列
+    pub fn files(&self) -> Vec<String> {
+        let cell_files = match &self.payload {
+            SpecialPayload::Grid(payload) => payload
+                .cells
+                .as_ref()
+                .map(|cells| cells.file_names.clone())
+                .unwrap_or_default(),
+            _ => vec![],
+        };
+
+        self.file_name.iter().cloned().chain(cell_files).collect()
+    }
+}
+
 pub fn idgen() -> String {
     uuid::Uuid::new_v4().to_string()
 }
@@ -0,0 +1,114 @@
+//! 所有验证类型共用的输出格式选项。
+
+use crate::{
+    captchas::namegen,
+    errors::{Error, Result},
+    fail,
+};
+use magick_rust::MagickWand;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OutputControl {
+    // 输出格式
+    pub format: Option<Format>,
+    // 压缩质量（1-100）
+    pub quality: Option<usize>,
+    // 无损压缩（仅 png、webp 和 avif 支持）
+    pub lossless: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Jpeg,
+    Png,
+    Webp,
+    Avif,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Jpeg => "jpg",
+            Format::Png => "png",
+            Format::Webp => "webp",
+            Format::Avif => "avif",
+        }
+    }
+
+    fn magick_format(&self) -> &'static str {
+        match self {
+            Format::Jpeg => "JPEG",
+            Format::Png => "PNG",
+            Format::Webp => "WEBP",
+            Format::Avif => "AVIF",
+        }
+    }
+}
+
+impl OutputControl {
+    pub fn check(&self) -> Result<()> {
+        if let Some(quality) = self.quality
+            && !(1..=100).contains(&quality)
+        {
+            return Err(Error::InvalidOutput(format!(
+                "quality must be between 1 and 100, provided: {quality}"
+            )));
+        }
+        if self.lossless.unwrap_or(false) && self.format.unwrap_or_default() == Format::Jpeg {
+            return Err(Error::InvalidOutput(
+                "lossless is not supported by jpeg".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// 按输出选项编码并写入图片，返回生成的文件名。
+pub fn write(wand: &mut MagickWand, out_base: &str, output: &OutputControl) -> Result<String> {
+    let format = output.format.unwrap_or_default();
+    // 移除元数据（EXIF、ICC 等）
+    wand.strip_image()?;
+    wand.set_image_format(format.magick_format())?;
+    if let Some(quality) = output.quality {
+        wand.set_image_compression_quality(quality)?;
+    }
+    if output.lossless.unwrap_or(false) {
+        match format {
+            Format::Webp => wand.set_option("webp:lossless", "true")?,
+            // AVIF 由 HEIC 编码器处理
+            Format::Avif => wand.set_option("heic:lossless", "true")?,
+            // PNG 本身无损，JPEG 不支持（已在检查时拒绝）
+            Format::Png | Format::Jpeg => {}
+        }
+    }
+
+    let file_name = format!("{}.{}", namegen(), format.extension());
+    let out_file = PathBuf::from(out_base).join(&file_name);
+    wand.write_image(out_file.to_str().ok_or(fail!("bad out file path"))?)?;
+
+    Ok(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let output: OutputControl =
+            serde_json::from_str(r#"{"format": "webp", "quality": 80, "lossless": true}"#).unwrap();
+        assert!(output.check().is_ok());
+        assert_eq!(output.format.unwrap().extension(), "webp");
+
+        let output: OutputControl = serde_json::from_str(r#"{"lossless": true}"#).unwrap();
+        assert!(matches!(output.check(), Err(Error::InvalidOutput(_))));
+
+        let output: OutputControl = serde_json::from_str(r#"{"quality": 0}"#).unwrap();
+        assert!(matches!(output.check(), Err(Error::InvalidOutput(_))));
+    }
+}
@@ -0,0 +1,94 @@
+//! 对图集图片做轻微的随机扰动，使输出无法通过文件哈希或感知哈希反查原图。
+
+use crate::errors::Result;
+use magick_rust::{FilterType, MagickEvaluateOperator, MagickWand};
+use rand::Rng;
+
+// 每条边最多剪裁的比例
+const MAX_CROP_RATIO: f64 = 0.02;
+// 缩放比例的范围
+const SCALE_RANGE: (f64, f64) = (0.97, 1.03);
+
+// 剪裁区域和缩放后的尺寸
+#[derive(Debug)]
+struct Geometry {
+    crop_width: usize,
+    crop_height: usize,
+    x: isize,
+    y: isize,
+    width: usize,
+    height: usize,
+}
+
+pub fn apply(wand: &mut MagickWand) -> Result<()> {
+    let mut rng = rand::rng();
+    let geometry = random_geometry(wand.get_image_width(), wand.get_image_height(), &mut rng);
+
+    // 随机剪裁边缘
+    wand.crop_image(
+        geometry.crop_width,
+        geometry.crop_height,
+        geometry.x,
+        geometry.y,
+    )?;
+    wand.reset_image_page("")?;
+    // 随机缩放
+    wand.resize_image(geometry.width, geometry.height, FilterType::Triangle)?;
+    // 随机偏移亮度、饱和度和色相（100 为不变）
+    wand.modulate_image(
+        rng.random_range(97.0..=103.0),
+        rng.random_range(95.0..=105.0),
+        rng.random_range(98.0..=102.0),
+    )?;
+    // 添加轻微的高斯噪声
+    wand.evaluate_image(
+        MagickEvaluateOperator::GaussianNoise,
+        rng.random_range(0.2..=0.6),
+    )?;
+
+    Ok(())
+}
+
+// 缩放基于剪裁后的尺寸，两个方向使用同一比例以保持宽高比
+fn random_geometry(width: usize, height: usize, rng: &mut impl Rng) -> Geometry {
+    let max_x = (width as f64 * MAX_CROP_RATIO) as usize;
+    let max_y = (height as f64 * MAX_CROP_RATIO) as usize;
+    let (left, right) = (rng.random_range(0..=max_x), rng.random_range(0..=max_x));
+    let (top, bottom) = (rng.random_range(0..=max_y), rng.random_range(0..=max_y));
+    let crop_width = width - left - right;
+    let crop_height = height - top - bottom;
+    let scale = rng.random_range(SCALE_RANGE.0..=SCALE_RANGE.1);
+
+    Geometry {
+        crop_width,
+        crop_height,
+        x: left as isize,
+        y: top as isize,
+        width: ((crop_width as f64 * scale).round() as usize).max(1),
+        height: ((crop_height as f64 * scale).round() as usize).max(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_geometry() {
+        let mut rng = rand::rng();
+
+        for (width, height) in [(250, 250), (640, 360), (180, 140), (1200, 300)] {
+            for _ in 0..100 {
+                let geometry = random_geometry(width, height, &mut rng);
+                // 缩放后的宽高比与剪裁后的一致（误差不超过取整造成的 1 像素）
+                let cropped_ratio = geometry.crop_width as f64 / geometry.crop_height as f64;
+                let ratio = geometry.width as f64 / geometry.height as f64;
+                assert!((ratio - cropped_ratio).abs() <= cropped_ratio / geometry.height as f64);
+                // 缩放比例在范围内，不会把剪裁的尺寸拉回原图尺寸
+                let scale = geometry.width as f64 / geometry.crop_width as f64;
+                assert!(scale >= SCALE_RANGE.0 - 0.01 && scale <= SCALE_RANGE.1 + 0.01);
+                assert!(geometry.crop_width <= width && geometry.crop_height <= height);
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! 根据每种类型、每种语言的模板渲染提示文本。
+//!
+//! 内置模板可以被清单中的 `prompts` 覆盖，模板支持 `{subject}` 和 `{count}` 两个占位符。
+
+use crate::{
+    models::payload::SpecialPayload,
+    provider::manifest::{I18nName, Manifest},
+    vars::LANG_FALLBACKS,
+};
+
+const DEFAULT_TEMPLATES: &[(&str, &str, &str)] = &[
+    ("classic", "zh-hans", "请输入图片中的字符"),
+    ("classic", "zh-hant", "請輸入圖片中的字元"),
+    ("classic", "en", "Enter the characters shown in the image"),
+    ("grid", "zh-hans", "请选择所有包含「{subject}」的图片编号"),
+    ("grid", "zh-hant", "請選擇所有包含「{subject}」的圖片編號"),
+    ("grid", "en", "Select all images with {subject}"),
+    ("image", "zh-hans", "这张图片是什么？"),
+    ("image", "zh-hant", "這張圖片是什麼？"),
+    ("image", "en", "What is shown in this image?"),
+];
+
+// 渲染指定语言的提示文本（未指定语言时使用回退链中的全部语言），`manifest` 为图集所属集合的清单
+// 模板或主题没有该语言的版本时不输出该语言，避免混杂不同语言
+pub fn render(
+    payload: &SpecialPayload,
+    manifest: Option<&Manifest>,
+    langs: Option<&[String]>,
+) -> I18nName {
+    let (subject, count) = match payload {
+        SpecialPayload::Classic(_) => (None, 1),
+        SpecialPayload::Grid(payload) => (Some(&payload.subject), payload.parts.len()),
+        SpecialPayload::Image(_) => (None, 1),
+    };
+    let templates = templates(payload.kind(), manifest);
+    let langs = langs.unwrap_or(&LANG_FALLBACKS);
+
+    langs
+        .iter()
+        .filter_map(|lang| {
+            let template = templates.resolve_tag(lang)?;
+            let subject = match subject {
+                Some(subject) => Some(subject.resolve_tag(lang)?),
+                None => None,
+            };
+
+            Some((lang.clone(), render_template(template, subject, count)))
+        })
+        .collect()
+}
+
+// 内置模板，被清单中的同语言模板覆盖
+fn templates(kind: &str, manifest: Option<&Manifest>) -> I18nName {
+    let defaults = DEFAULT_TEMPLATES
+        .iter()
+        .filter(|(k, _, _)| *k == kind)
+        .map(|(_, lang, template)| (lang.to_string(), template.to_string()));
+    let overrides = manifest
+        .and_then(|manifest| manifest.prompts.as_ref()?.get(kind).cloned())
+        .unwrap_or_default();
+
+    defaults.chain(overrides).collect()
+}
+
+fn render_template(template: &str, subject: Option<&String>, count: usize) -> String {
+    template
+        .replace("{subject}", subject.map(String::as_str).unwrap_or_default())
+        .replace("{count}", &count.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let subject = "Cat".to_string();
+
+        assert_eq!(
+            render_template(
+                "Select all {count} images with {subject}",
+                Some(&subject),
+                3
+            ),
+            "Select all 3 images with Cat"
+        );
+        assert_eq!(render_template("What is it?", None, 1), "What is it?");
+    }
+
+    #[test]
+    fn test_render_langs() {
+        let payload = SpecialPayload::Classic(crate::captchas::classic::Payload {
+            text: "abcd".to_string(),
+            choices: vec![],
+        });
+        let langs = ["ja".to_string(), "en-US".to_string()];
+        let prompt = render(&payload, None, Some(&langs));
+
+        // 没有日语模板时不输出日语，而不是使用其它语言的模板
+        assert_eq!(prompt.get("ja"), None);
+        assert_eq!(
+            prompt.get("en-us"),
+            Some(&"Enter the characters shown in the image".to_string())
+        );
+    }
+}
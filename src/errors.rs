@@ -31,9 +31,31 @@ pub enum Error {
     #[strum(props(code = 103))]
     #[error("namespace cannot start with a slash")]
     NamespaceStartsWithSlash,
+    // 无效的作答时间窗口
+    #[strum(props(code = 104))]
+    #[error("invalid solve window: min_solve_ms ({min}) is greater than max_solve_ms ({max})")]
+    InvalidSolveWindow { min: u64, max: u64 },
+    // 无效的会话参数
+    #[strum(props(code = 105))]
+    #[error("invalid session: {0}")]
+    InvalidSession(String),
+    // 按钮数量超出键盘容量
+    #[strum(props(code = 106))]
+    #[error("too many buttons for the keyboard: {buttons} exceed the capacity of {capacity}")]
+    KeyboardOverflow { buttons: usize, capacity: usize },
+    // 无效的输出选项
+    #[strum(props(code = 107))]
+    #[error("invalid output: {0}")]
+    InvalidOutput(String),
+    // 集合名称包含非法字符
+    #[strum(props(code = 108))]
+    #[error(
+        "illegal collection name: {0}, only lowercase letters, numbers, underscores, and hyphens are allowed"
+    )]
+    IllegalCollection(String),
     // 无效的网格布局
     #[strum(props(code = 110))]
-    #[error("invalid grid layout: {0}, only '3x3' is supported")]
+    #[error("invalid grid layout: {0}, expected '<columns>x<rows>' with each side between 2 and 5")]
     InvalidGridLayout(String),
     // 没有已上传的压缩包
     #[strum(props(code = 410))]
@@ -43,6 +65,22 @@ pub enum Error {
     #[strum(props(status_code = 404, code = 411))]
     #[error("verification cache not found: {0}")]
     VerificationCacheNotFound(String),
+    // 未找到会话
+    #[strum(props(status_code = 404, code = 412))]
+    #[error("session not found: {0}")]
+    SessionNotFound(String),
+    // 未找到图集集合
+    #[strum(props(status_code = 404, code = 413))]
+    #[error("album collection not found: {0}")]
+    CollectionNotFound(String),
+    // 终端用户处于冷却期
+    #[strum(props(status_code = 429, code = 420))]
+    #[error("the subject has failed too many times, retry after {retry_after_secs} seconds")]
+    SubjectCoolingDown { retry_after_secs: u64 },
+    // 终端用户与生成时绑定的用户不一致
+    #[strum(props(status_code = 403, code = 421))]
+    #[error("the subject does not match the one bound to the challenge")]
+    SubjectMismatch,
     // 未授权
     #[strum(props(status_code = 401))]
     #[error("unauthorized access")]
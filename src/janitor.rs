@@ -62,6 +62,14 @@ impl TTLJanitor {
         Ok(())
     }
 
+    async fn remove_image(&self, path: &Path) -> bool {
+        let mut queue = self.expiration_queue.lock().await;
+        let before = queue.len();
+        queue.retain(|image| image.path != path);
+
+        queue.len() < before
+    }
+
     async fn cleanup_expired(&self) -> usize {
         let mut removed_total = 0;
         let mut queue = self.expiration_queue.lock().await;
@@ -219,6 +227,34 @@ pub async fn collect(base_dir: String, name: &str, ttl_secs: u64) {
     }
 }
 
+pub async fn collect_all(base_dir: String, names: &[String], ttl_secs: u64) {
+    for name in names {
+        collect(base_dir.clone(), name, ttl_secs).await;
+    }
+}
+
+// 立即删除图片，并将其移出过期队列
+pub async fn discard(base_dir: String, name: &str) -> Result<()> {
+    let path = PathBuf::from(base_dir).join(name);
+    if !ttl_janitor().remove_image(&path).await {
+        debug!("Image not found in janitor queue: {}", path.display());
+    }
+    if path.exists() {
+        fs::remove_file(&path)?;
+        debug!("Discarded image: {}", path.display());
+    }
+
+    Ok(())
+}
+
+pub async fn discard_all(base_dir: String, names: &[String]) -> Result<()> {
+    for name in names {
+        discard(base_dir.clone(), name).await?;
+    }
+
+    Ok(())
+}
+
 pub async fn ttl_cleanup() {
     debug!("Starting cleanup of expired images...");
     let removed_total = ttl_janitor().cleanup_expired().await;
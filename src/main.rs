@@ -32,6 +32,7 @@ mod logger;
 mod middlewares;
 mod models;
 mod provider;
+mod reputation;
 mod routes;
 mod scueduler;
 mod vars;
@@ -67,7 +68,7 @@ async fn web_serve(bind: &str) -> Result<()> {
     provider::init();
     // Is API authentication enabled
     let is_auth_enabled = !(*CAPINDE_API_KEY).is_empty();
-    // Provider routes
+    // Provider routes (the default collection, or a named one under `/{collection}`)
     let provider_routes = Router::new()
         .route("/deployed", get(routes::provider::deployed))
         .route("/uploaded", get(routes::provider::get_uploaded))
@@ -83,13 +84,28 @@ async fn web_serve(bind: &str) -> Result<()> {
         .route("/status", get(routes::janitor::status))
         .route("/schedule", put(routes::janitor::schedule));
 
+    // Challenge routes
+    let challenge_routes = Router::new()
+        .route("/{unique_id}", get(routes::challenge::status))
+        .route("/{unique_id}", delete(routes::challenge::revoke))
+        .route("/{unique_id}/refresh", post(routes::challenge::refresh));
+
+    // Session routes
+    let session_routes = Router::new()
+        .route("/", post(routes::session::create))
+        .route("/{session_id}", get(routes::session::status))
+        .route("/{session_id}/verify", post(routes::session::verify));
+
     // Server routes
     let server_routes = Router::new().route("/info", get(routes::server::info));
 
     let mut app = Router::new()
         .route("/api/generate", post(routes::generate))
         .route("/api/verify", post(routes::verify))
-        .nest("/api/provider", provider_routes)
+        .nest("/api/challenges", challenge_routes)
+        .nest("/api/sessions", session_routes)
+        .nest("/api/provider", provider_routes.clone())
+        .nest("/api/provider/{collection}", provider_routes)
         .nest("/api/janitor", janitor_routes)
         .nest("/api/server", server_routes)
         .route("/api/healthcheck", get(routes::healthcheck));
@@ -1,16 +1,120 @@
-use crate::captchas;
+use crate::{captchas, errors::Result, provider};
 use serde::Deserialize;
+use std::collections::BTreeSet;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Input {
     pub namespace: String,
     pub ttl_secs: Option<u64>,
+    // 最短作答时间（毫秒），低于此时间的作答将被拒绝
+    pub min_solve_ms: Option<u64>,
+    // 最长作答时间（毫秒），超出此时间的作答将被拒绝
+    pub max_solve_ms: Option<u64>,
+    // 终端用户的标识（如 Telegram 用户 ID 或会话哈希），验证时必须匹配
+    pub subject_id: Option<String>,
+    // 已弃用：答案总是同时以原生形式和索引形式存储
+    // 为 true 时沿用旧行为，只接受索引形式的答案（未生成候选项时也可以）
     pub use_index: Option<bool>,
+    // 提示文本的语言（BCP-47 标签），默认为语言回退链中的全部语言
+    pub prompt_langs: Option<Vec<String>>,
+    // 输出格式
+    pub output: Option<captchas::output::OutputControl>,
+    // 聊天键盘的排列限制，提供时生成键盘布局
+    pub keyboard: Option<captchas::keyboard::KeyboardControl>,
     #[serde(flatten)]
     pub choices_control: Option<ChoicesControl>,
+    // 图集集合的名称，默认为默认集合（仅用于网格和图片验证）
+    pub collection: Option<String>,
+    // 图集和标签过滤（仅用于网格和图片验证）
+    #[serde(flatten)]
+    pub album_filter: Option<AlbumFilter>,
     pub special_params: SpecialParams,
 }
 
+impl Input {
+    // 按等级提升生成难度
+    pub fn escalate(&mut self, level: usize) -> Result<()> {
+        let choices_control = self.choices_control.get_or_insert_default();
+        match &mut self.special_params {
+            SpecialParams::Classic(params) => {
+                captchas::classic::escalate(params, choices_control, level)
+            }
+            SpecialParams::Grid(params) => captchas::grid::escalate(params, choices_control, level),
+            SpecialParams::Image(_) => {
+                // 图片验证的错误答案必须来自不同的图集
+                let collection = provider::get_collection(self.collection.as_deref())?;
+                let album_filter = self.album_filter.clone().unwrap_or_default();
+                captchas::image::escalate(
+                    choices_control,
+                    level,
+                    collection.max_choices(&album_filter),
+                )
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// 测试用的最简参数：默认选项的传统验证
+#[cfg(test)]
+impl Input {
+    pub fn classic(namespace: &str) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            ttl_secs: None,
+            min_solve_ms: None,
+            max_solve_ms: None,
+            subject_id: None,
+            use_index: None,
+            prompt_langs: None,
+            output: None,
+            keyboard: None,
+            choices_control: None,
+            collection: None,
+            album_filter: None,
+            special_params: SpecialParams::Classic(Default::default()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AlbumFilter {
+    // 仅使用这些图集
+    pub include_albums: Option<Vec<String>>,
+    // 不使用这些图集，也不使用带有这些图集标签的图片
+    pub exclude_albums: Option<Vec<String>>,
+    // 仅使用带有其中任一标签的图片
+    pub include_tags: Option<Vec<String>>,
+    // 不使用带有其中任一标签的图片
+    pub exclude_tags: Option<Vec<String>>,
+}
+
+impl AlbumFilter {
+    pub fn allows_album(&self, album_id: &str) -> bool {
+        self.include_albums
+            .as_ref()
+            .is_none_or(|ids| ids.iter().any(|id| id == album_id))
+            && !self
+                .exclude_albums
+                .iter()
+                .flatten()
+                .any(|id| id == album_id)
+    }
+
+    pub fn allows_image(&self, tags: &BTreeSet<String>) -> bool {
+        self.include_tags
+            .as_ref()
+            .is_none_or(|include| include.iter().any(|tag| tags.contains(tag)))
+            && !self
+                .exclude_tags
+                .iter()
+                .chain(&self.exclude_albums)
+                .flatten()
+                .any(|tag| tags.contains(tag))
+    }
+}
+
 #[derive(Debug, Clone, Default, Deserialize)]
 pub struct ChoicesControl {
     // 启用候选项
@@ -25,22 +129,102 @@ pub enum SpecialParams {
     #[serde(rename = "classic")]
     Classic(captchas::classic::Params),
     #[serde(rename = "grid")]
-    Grid(captchas::grid::Params),
+    Grid(Box<captchas::grid::Params>),
     #[serde(rename = "image")]
     Image(captchas::image::Params),
 }
 
+impl SpecialParams {
+    // 验证类型（和序列化的 `type` 标签一致）
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SpecialParams::Classic(_) => "classic",
+            SpecialParams::Grid(_) => "grid",
+            SpecialParams::Image(_) => "image",
+        }
+    }
+}
+
+pub mod session {
+    use super::verification::Answer;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Deserialize)]
+    #[serde(try_from = "RawInput")]
+    pub struct Input {
+        pub namespace: String,
+        pub ttl_secs: Option<u64>,
+        pub subject_id: Option<String>,
+        // 通过会话所需的成功轮数（默认全部轮次）
+        pub pass_threshold: Option<usize>,
+        // 每一轮的生成参数（其中的 namespace、ttl_secs 和 subject_id 由会话覆盖）
+        pub rounds: Vec<super::Input>,
+    }
+
+    // 轮次参数可以省略 namespace，解析时由会话的 namespace 填充
+    #[derive(Deserialize)]
+    struct RawInput {
+        namespace: String,
+        ttl_secs: Option<u64>,
+        subject_id: Option<String>,
+        pass_threshold: Option<usize>,
+        rounds: Vec<serde_json::Map<String, serde_json::Value>>,
+    }
+
+    impl TryFrom<RawInput> for Input {
+        type Error = serde_json::Error;
+
+        fn try_from(raw: RawInput) -> Result<Self, Self::Error> {
+            let rounds = raw
+                .rounds
+                .into_iter()
+                .map(|mut round| {
+                    round.insert("namespace".to_string(), raw.namespace.clone().into());
+                    serde_json::from_value(round.into())
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(Input {
+                namespace: raw.namespace,
+                ttl_secs: raw.ttl_secs,
+                subject_id: raw.subject_id,
+                pass_threshold: raw.pass_threshold,
+                rounds,
+            })
+        }
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct Verification {
+        pub subject_id: Option<String>,
+        pub answer: Answer,
+    }
+}
+
+pub mod challenge {
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Default, Deserialize)]
+    pub struct Refresh {
+        // 终端用户的标识，需和生成时提供的一致
+        pub subject_id: Option<String>,
+    }
+}
+
 pub mod verification {
     use crate::{
         err,
         errors::Error,
         models::{params::SpecialParams, payload::SpecialPayload},
+        provider::manifest::I18nName,
     };
     use serde::Deserialize;
 
     #[derive(Debug, Clone, Deserialize)]
     pub struct Input {
         pub unique_id: String,
+        // 终端用户的标识，需和生成时提供的一致
+        pub subject_id: Option<String>,
         pub answer: Answer,
     }
 
@@ -55,6 +239,9 @@ pub mod verification {
         Image(Image),
         #[serde[rename = "index"]]
         Index { value: usize },
+        // 终端用户输入的原始文本
+        #[serde(rename = "text")]
+        Text { value: String },
     }
 
     #[derive(Debug, Clone, Deserialize)]
@@ -75,12 +262,12 @@ pub mod verification {
 
     #[derive(Debug, Clone, Deserialize)]
     pub struct Image {
-        // 简体答案
-        pub zh_hant: Option<String>,
-        // 繁体答案
-        pub zh_hans: Option<String>,
-        // 英文答案
-        pub en: Option<String>,
+        // 各语言的答案（以语言标签为键，兼容 `zh_hans` 等旧键）
+        #[serde(flatten)]
+        pub name: I18nName,
+        // 别名（仅存在于缓存的答案中）
+        #[serde(skip)]
+        pub aliases: Vec<String>,
     }
 
     impl TryFrom<(&SpecialParams, &SpecialPayload)> for Answer {
@@ -110,9 +297,8 @@ pub mod verification {
                 }
                 (SpecialParams::Image(_params), SpecialPayload::Image(payload)) => {
                     Ok(Answer::Image(Image {
-                        zh_hans: payload.name.zh_hans.clone(),
-                        zh_hant: payload.name.zh_hant.clone(),
-                        en: payload.name.en.clone(),
+                        name: payload.name.clone(),
+                        aliases: payload.aliases.clone(),
                     }))
                 }
 
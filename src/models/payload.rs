@@ -1,4 +1,7 @@
-use crate::{captchas, provider::manifest::Manifest};
+use crate::{
+    captchas,
+    provider::manifest::{I18nName, Manifest},
+};
 use chrono::{DateTime, Utc};
 use serde::Serialize;
 
@@ -30,8 +33,14 @@ pub struct Generated {
     pub working_mode: &'static str,
     pub namespace: String,
     pub unique_id: String,
-    pub file_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
     pub right_index: usize,
+    // 本地化的提示文本
+    pub prompt: I18nName,
+    // 聊天键盘布局
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keyboard: Option<captchas::keyboard::Keyboard>,
     pub special_payload: SpecialPayload,
 }
 
@@ -46,13 +55,112 @@ pub enum SpecialPayload {
     Image(captchas::image::Payload),
 }
 
+impl SpecialPayload {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SpecialPayload::Classic(_) => "classic",
+            SpecialPayload::Grid(_) => "grid",
+            SpecialPayload::Image(_) => "image",
+        }
+    }
+
+    // 是否生成了候选项
+    pub fn has_choices(&self) -> bool {
+        match self {
+            SpecialPayload::Classic(payload) => !payload.choices.is_empty(),
+            SpecialPayload::Grid(payload) => !payload.choices.is_empty(),
+            SpecialPayload::Image(payload) => !payload.choices.is_empty(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct DeployedInfo {
+    pub collection: String,
     pub manifest: Manifest,
     pub total_images: usize,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct ChallengeStatus {
+    pub exists: bool,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub details: Option<ChallengeDetails>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChallengeDetails {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub namespace: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    // 已验证次数
+    pub attempts: usize,
+    // 最近一次的验证结果
+    pub outcome: Option<VefifyResult>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionState {
+    pub session_id: String,
+    pub status: SessionStatus,
+    // 当前轮次（从 1 开始）
+    pub round: usize,
+    pub total_rounds: usize,
+    pub successes: usize,
+    pub pass_threshold: usize,
+    // 当前轮次的验证 ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unique_id: Option<String>,
+    // 上一轮的验证结果
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_result: Option<VefifyResult>,
+    // 新生成的轮次
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated: Option<Generated>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStatus {
+    Pending,
+    Passed,
+    Failed,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct VefifyResult {
     pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<FailureReason>,
+}
+
+impl VefifyResult {
+    pub fn passed() -> Self {
+        Self {
+            ok: true,
+            reason: None,
+        }
+    }
+
+    pub fn failed(reason: FailureReason) -> Self {
+        Self {
+            ok: false,
+            reason: Some(reason),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureReason {
+    // 答案错误
+    WrongAnswer,
+    // 作答过快（低于最短作答时间）
+    TooFast,
+    // 作答过慢（超出最长作答时间）
+    TooSlow,
+    // 终端用户标识不匹配
+    SubjectMismatch,
 }
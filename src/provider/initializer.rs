@@ -1,83 +1,92 @@
-use super::{ALBUM_IMAGES, MANIFEST, manifest, manifest::Album};
+use super::{
+    Collection, DEFAULT_COLLECTION, TaggedImage, collection_base, manifest,
+    manifest::{Album, Manifest},
+    reset_collection,
+};
 use crate::{
     err,
-    errors::Result,
+    errors::{Error, Result},
     fail,
-    provider::{
-        CONFLICTS, Conflicts, get_manifest, manifest::Manifest, reset_album_images,
-        reset_conflicts, reset_manifest,
-    },
-    vars::CAPINDE_ALBUMS_BASE,
+    vars::{CAPINDE_ALBUMS_BASE, CAPINDE_COLLECTIONS_BASE},
 };
 use log::{debug, error, info, warn};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
     path::{Path, PathBuf},
-    sync::{Once, RwLock},
+    sync::Once,
 };
 
+// 图集目录中的图片标签文件
+const TAGS_SIDECAR: &str = "tags.yaml";
+const MANIFEST_FILE_NAME: &str = "Manifest.yaml";
+
 static INIT: Once = Once::new();
 
 pub fn init() {
-    let albums_base = PathBuf::from(&*CAPINDE_ALBUMS_BASE);
-    if CAPINDE_ALBUMS_BASE.is_empty() || !albums_base.exists() {
-        warn!(
-            "Variable `CAPINDE_ALBUMS_BASE` is not set or the directory does not exist, skipping initialization"
-        );
-    } else {
-        INIT.call_once(|| match run(albums_base) {
-            Ok(_) => {
-                info!("Provider initialized successfully");
-            }
-            Err(e) => {
-                error!("Failed to initialize provider: {e}");
+    INIT.call_once(|| {
+        let albums_base = PathBuf::from(&*CAPINDE_ALBUMS_BASE);
+        if CAPINDE_ALBUMS_BASE.is_empty() || !albums_base.exists() {
+            warn!(
+                "Variable `CAPINDE_ALBUMS_BASE` is not set or the directory does not exist, skipping initialization"
+            );
+        } else {
+            run_logged(DEFAULT_COLLECTION, albums_base);
+        }
+        // 集合目录下每个包含清单文件的子目录都是一个命名集合
+        if let Ok(entries) = std::fs::read_dir(*CAPINDE_COLLECTIONS_BASE) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if let Some(name) = path.file_name().and_then(|name| name.to_str())
+                    && path.join(MANIFEST_FILE_NAME).is_file()
+                {
+                    match collection_base(name) {
+                        Ok(collection_base) => run_logged(name, collection_base),
+                        Err(e) => warn!("Skipping collection directory {path:?}: {e}"),
+                    }
+                }
             }
-        });
-    }
+        }
+    });
 }
 
-pub fn reinit(albums_base: PathBuf) -> Result<()> {
-    if INIT.is_completed() {
-        info!("Provider is already initialized, reinitializing...");
-        let manifest = load_manifest(albums_base)?;
-        let album_images = load_album_images(&manifest);
-
-        reset_manifest(manifest.clone())?;
-        reset_album_images(album_images);
-        reset_conflicts(Conflicts::from(&manifest.conflicts.unwrap_or(vec![])));
-    } else {
+/// 重新加载指定的集合（集合尚未加载时会被添加）。
+pub fn reinit(name: &str) -> Result<()> {
+    if !INIT.is_completed() {
         info!("Provider is not initialized, initializing now...");
-
         init();
     }
+    let albums_base = collection_base(name)?;
+    if name != DEFAULT_COLLECTION && !albums_base.join(MANIFEST_FILE_NAME).is_file() {
+        return Err(Error::CollectionNotFound(name.to_string()));
+    }
+    info!("Reloading collection `{name}`...");
 
-    Ok(())
+    run(name, albums_base)
 }
 
-fn run(albums_base: PathBuf) -> Result<()> {
-    // 初始化全局的清单配置
-    let manifest = load_manifest(albums_base)?;
-    MANIFEST
-        .set(RwLock::new(manifest.clone()))
-        .expect("Failed to set manifest");
-    // 初始化全局的图集和图片列表映射
-    let album_images = load_album_images(&manifest);
-    ALBUM_IMAGES
-        .set(RwLock::new(album_images))
-        .expect("Failed to set album images");
-
-    CONFLICTS
-        .set(RwLock::new(Conflicts::from(
-            &manifest.conflicts.unwrap_or(vec![]),
-        )))
-        .expect("Failed to set conflicts");
-
-    Ok(())
+fn run_logged(name: &str, albums_base: PathBuf) {
+    match run(name, albums_base) {
+        Ok(_) => {
+            info!("Collection `{name}` initialized successfully");
+        }
+        Err(e) => {
+            error!("Failed to initialize collection `{name}`: {e}");
+        }
+    }
+}
+
+fn run(name: &str, albums_base: PathBuf) -> Result<()> {
+    // 加载清单配置
+    let manifest = load_manifest(&albums_base)?;
+    // 加载图集和图片列表映射
+    let album_images = load_album_images(&manifest, &albums_base);
+
+    reset_collection(name, Collection::new(manifest, album_images))
 }
 
-fn load_manifest(albums_base: PathBuf) -> Result<Manifest> {
+fn load_manifest(albums_base: &Path) -> Result<Manifest> {
     // 从路径加载清单文件
-    let file_path = albums_base.join("Manifest.yaml");
+    let file_path = albums_base.join(MANIFEST_FILE_NAME);
 
     if file_path.exists() {
         manifest::load(&file_path)
@@ -91,6 +100,8 @@ fn load_manifest(albums_base: PathBuf) -> Result<Manifest> {
             albums: vec![],
             include_formats: vec!["jpg".to_string(), "png".to_string()],
             conflicts: Some(vec![]),
+            similarities: None,
+            prompts: None,
         };
         manifest.save(&file_path)?;
 
@@ -103,12 +114,12 @@ fn load_manifest(albums_base: PathBuf) -> Result<Manifest> {
     }
 }
 
-fn load_album_images(manifest: &Manifest) -> HashMap<String, Vec<PathBuf>> {
+fn load_album_images(manifest: &Manifest, albums_base: &Path) -> HashMap<String, Vec<TaggedImage>> {
     debug!("Loading album images...");
     // 扫描所有图集并存储图片路径
     let mut album_images = HashMap::new();
     for album in manifest.albums.iter() {
-        match scan_images(album) {
+        match scan_images(album, albums_base, &manifest.include_formats) {
             Ok(images) => {
                 info!(
                     "Successfully loaded {} album: {} image(s)",
@@ -124,19 +135,32 @@ fn load_album_images(manifest: &Manifest) -> HashMap<String, Vec<PathBuf>> {
     album_images
 }
 
-fn scan_images(album: &Album) -> Result<Vec<PathBuf>> {
-    let dir_path = PathBuf::from(&*CAPINDE_ALBUMS_BASE).join(&album.id);
+fn scan_images(
+    album: &Album,
+    albums_base: &Path,
+    include_formats: &[String],
+) -> Result<Vec<TaggedImage>> {
+    let dir_path = albums_base.join(&album.id);
     if dir_path.exists() && dir_path.is_dir() {
         let mut images = vec![];
         let entries = std::fs::read_dir(&dir_path)
             .map_err(|e| fail!("Failed to read album directory: {}", e))?;
+        let extra_tags = load_tags(album, &dir_path)?;
 
         for entry in entries {
             let entry = entry.map_err(|e| fail!("failed to read entry: {}", e))?;
             let path = entry.path();
 
-            if path.is_file() && includes_format(&path)? {
-                images.push(path);
+            if path.is_file() && includes_format(&path, include_formats) {
+                let mut tags = BTreeSet::from([album.id.clone()]);
+                if let Some(extra) = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| extra_tags.get(name))
+                {
+                    tags.extend(extra.iter().cloned());
+                }
+                images.push(TaggedImage { path, tags });
             } else if path.is_dir() {
                 warn!("Skipping subdirectory in album: {path:?}");
             }
@@ -155,11 +179,23 @@ fn scan_images(album: &Album) -> Result<Vec<PathBuf>> {
     }
 }
 
-fn includes_format(path: &Path) -> Result<bool> {
-    let is_includes = get_manifest()?
-        .include_formats
-        .iter()
-        .any(|ext| path.extension().is_some_and(|e| e.to_str() == Some(ext)));
+// 合并清单和标签文件中声明的附加标签
+fn load_tags(album: &Album, dir_path: &Path) -> Result<BTreeMap<String, Vec<String>>> {
+    let mut tags = album.tags.clone().unwrap_or_default();
+    let sidecar = dir_path.join(TAGS_SIDECAR);
+    if sidecar.is_file() {
+        let file = std::fs::File::open(&sidecar)?;
+        let sidecar_tags: BTreeMap<String, Vec<String>> = serde_yaml::from_reader(file)?;
+        for (file_name, extra) in sidecar_tags {
+            tags.entry(file_name).or_default().extend(extra);
+        }
+    }
 
-    Ok(is_includes)
+    Ok(tags)
+}
+
+fn includes_format(path: &Path, include_formats: &[String]) -> bool {
+    include_formats
+        .iter()
+        .any(|ext| path.extension().is_some_and(|e| e.to_str() == Some(ext)))
 }
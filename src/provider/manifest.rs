@@ -1,12 +1,17 @@
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
 
-use crate::errors::{Error, Result};
+use crate::{
+    err,
+    errors::{Error, Result},
+    vars::LANG_FALLBACKS,
+};
 
 pub static LATEST_VERSION: &str = "0.1.2";
+const FALLBACK_WEIGHT: f64 = 1.0;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Manifest {
     pub version: String,
     pub datetime: DateTime<Utc>,
@@ -14,12 +19,61 @@ pub struct Manifest {
     pub include_formats: Vec<String>,
     pub albums: Vec<Album>,
     pub conflicts: Option<Vec<Vec<String>>>,
+    // 相似（容易混淆）的图集组，用于挑选有迷惑性的错误答案
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarities: Option<Vec<Vec<String>>>,
+    // 按验证类型覆盖提示文本模板
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompts: Option<BTreeMap<String, I18nName>>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Album {
     pub id: String,
     pub name: I18nName,
+    // 各语言的别名（同义词），以语言标签为键
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aliases: Option<BTreeMap<String, Vec<String>>>,
+    // 各图片的焦点，以文件名为键，剪裁时以焦点为中心
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focal_points: Option<BTreeMap<String, FocalPoint>>,
+    // 各图片的附加标签（图片中出现的其它图集 ID），以文件名为键
+    // 图片总是带有所属图集 ID 的标签，也可以在图集目录的 `tags.yaml` 中声明
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<BTreeMap<String, Vec<String>>>,
+    // 随机选择时的相对权重，默认为 1，为 0 时仅在没有其它图集可选时才会被选中
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+}
+
+// 相对坐标（0.0 - 1.0）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FocalPoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl FocalPoint {
+    pub fn is_valid(&self) -> bool {
+        (0.0..=1.0).contains(&self.x) && (0.0..=1.0).contains(&self.y)
+    }
+}
+
+impl Album {
+    // 所有语言的别名
+    pub fn all_aliases(&self) -> Vec<String> {
+        self.aliases
+            .iter()
+            .flat_map(|aliases| aliases.values().flatten().cloned())
+            .collect()
+    }
+
+    // 随机选择时的相对权重（无效值使用默认权重）
+    pub fn weight(&self) -> f64 {
+        self.weight
+            .filter(|weight| weight.is_finite() && *weight >= 0.0)
+            .unwrap_or(FALLBACK_WEIGHT)
+    }
 }
 
 impl std::fmt::Display for Album {
@@ -28,20 +82,122 @@ impl std::fmt::Display for Album {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct I18nName {
-    #[serde(rename = "zh-hans")]
-    pub zh_hans: Option<String>,
-    #[serde(rename = "zh-hant")]
-    pub zh_hant: Option<String>,
-    pub en: Option<String>,
+// 以语言标签（BCP-47）为键的名称
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct I18nName(BTreeMap<String, String>);
+
+impl I18nName {
+    pub fn get(&self, lang: &str) -> Option<&String> {
+        self.0.get(&normalize_lang(lang))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.0.values()
+    }
+
+    // 按语言回退：先尝试完整标签及其上级标签，再依次尝试回退链中的语言，最后使用任意一个
+    pub fn resolve(&self, lang: &str) -> Option<&String> {
+        self.resolve_tag(lang).or_else(|| {
+            LANG_FALLBACKS
+                .iter()
+                .find_map(|lang| self.0.get(lang))
+                .or_else(|| self.0.values().next())
+        })
+    }
+
+    // 仅在同一语言内回退：尝试完整标签及其上级标签（如 `zh-hans-cn` -> `zh-hans` -> `zh`）
+    pub fn resolve_tag(&self, lang: &str) -> Option<&String> {
+        let mut lang = normalize_lang(lang);
+        loop {
+            if let Some(name) = self.0.get(&lang) {
+                return Some(name);
+            }
+            match lang.rfind('-') {
+                Some(i) => lang.truncate(i),
+                None => return None,
+            }
+        }
+    }
+
+    // 包含的语言，回退链中的语言按顺序排在前面
+    pub fn languages(&self) -> Vec<&str> {
+        let mut languages = LANG_FALLBACKS
+            .iter()
+            .filter(|lang| self.0.contains_key(*lang))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        for lang in self.0.keys() {
+            if !LANG_FALLBACKS.contains(lang) {
+                languages.push(lang);
+            }
+        }
+
+        languages
+    }
+}
+
+impl IntoIterator for I18nName {
+    type Item = (String, String);
+    type IntoIter = std::collections::btree_map::IntoIter<String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl FromIterator<(String, String)> for I18nName {
+    fn from_iter<T: IntoIterator<Item = (String, String)>>(iter: T) -> Self {
+        I18nName(
+            iter.into_iter()
+                .map(|(lang, name)| (normalize_lang(&lang), name))
+                .collect(),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for I18nName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        // 兼容旧格式中值为空的语言
+        let names = BTreeMap::<String, Option<String>>::deserialize(deserializer)?;
+
+        Ok(names
+            .into_iter()
+            .filter_map(|(lang, name)| name.map(|name| (lang, name)))
+            .collect())
+    }
+}
+
+// 规范化语言标签，如 `zh_Hans` -> `zh-hans`
+pub fn normalize_lang(lang: &str) -> String {
+    lang.trim().replace('_', "-").to_ascii_lowercase()
 }
 
 impl Manifest {
     fn load(path: &PathBuf) -> Result<Manifest> {
         let file = std::fs::File::open(path)?;
+        let manifest: Manifest = serde_yaml::from_reader(file)?;
+        manifest.check()?;
+
+        Ok(manifest)
+    }
 
-        Ok(serde_yaml::from_reader(file)?)
+    // 检查无法由类型约束的字段
+    fn check(&self) -> Result<()> {
+        for album in &self.albums {
+            for (file, focal_point) in album.focal_points.iter().flatten() {
+                if !focal_point.is_valid() {
+                    return err!(
+                        "invalid focal point of {file} in album {}: ({}, {}), expected coordinates between 0.0 and 1.0",
+                        album.id,
+                        focal_point.x,
+                        focal_point.y
+                    );
+                }
+            }
+        }
+
+        Ok(())
     }
 
     pub fn save(&self, path: &PathBuf) -> Result<()> {
@@ -56,6 +212,7 @@ impl std::str::FromStr for Manifest {
     type Err = Error;
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         let manifest: Manifest = serde_yaml::from_str(s)?;
+        manifest.check()?;
 
         Ok(manifest)
     }
@@ -83,12 +240,73 @@ mod tests {
         assert_eq!(manifest.include_formats, vec!["jpg", "png"]);
         assert_eq!(manifest.albums.len(), 10);
         assert_eq!(manifest.albums[0].id, "cats");
-        assert_eq!(manifest.albums[0].name.zh_hans, Some("猫".to_string()));
-        assert_eq!(manifest.albums[0].name.zh_hant, Some("貓".to_string()));
-        assert_eq!(manifest.albums[0].name.en, Some("Cat".to_string()));
+        assert_eq!(
+            manifest.albums[0].name.get("zh-hans"),
+            Some(&"猫".to_string())
+        );
+        assert_eq!(
+            manifest.albums[0].name.get("zh-hant"),
+            Some(&"貓".to_string())
+        );
+        assert_eq!(manifest.albums[0].name.get("en"), Some(&"Cat".to_string()));
+        assert_eq!(
+            manifest.albums[0].all_aliases(),
+            vec!["Kitty", "Kitten", "猫咪"]
+        );
+        assert_eq!(manifest.albums[1].aliases, None);
+        assert_eq!(
+            manifest.albums[5].tags.as_ref().unwrap()["09a3106158f05b1.jpg"],
+            vec!["birds"]
+        );
+        assert_eq!(
+            manifest.albums[2].focal_points.as_ref().unwrap()["3cd074e73e27628.jpg"],
+            FocalPoint { x: 0.5, y: 0.4 }
+        );
         assert!(manifest.conflicts.as_ref().unwrap().len() > 0);
+        assert_eq!(
+            manifest.similarities,
+            Some(vec![
+                vec!["airplanes".to_string(), "birds".to_string()],
+                vec!["coins".to_string(), "watches".to_string()],
+            ])
+        );
         for conflict in manifest.conflicts.as_ref().unwrap() {
             assert!(conflict.len() > 0);
         }
     }
+
+    #[test]
+    fn test_check_focal_points() {
+        let manifest = |x: f64, y: f64| {
+            format!(
+                "version: {LATEST_VERSION}\ndatetime: 1970-01-01T00:00:00Z\ninclude_formats: [jpg]\n\
+                 albums:\n  - id: dogs\n    name: {{ en: Dog }}\n    focal_points:\n      \
+                 a.jpg: {{ x: {x}, y: {y} }}\nconflicts: []\n"
+            )
+        };
+
+        assert!(manifest(0.0, 1.0).parse::<Manifest>().is_ok());
+        assert!(manifest(1.2, 0.5).parse::<Manifest>().is_err());
+        assert!(manifest(0.5, -0.1).parse::<Manifest>().is_err());
+    }
+
+    #[test]
+    fn test_i18n_name() {
+        let name: I18nName = serde_json::from_str(
+            r#"{"zh_Hans": "猫", "zh-hant": null, "en": "Cat", "ja": "猫", "ru": "Кошка"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(name.get("zh-hans"), Some(&"猫".to_string()));
+        assert_eq!(name.get("ZH_HANS"), Some(&"猫".to_string()));
+        assert_eq!(name.get("zh-hant"), None);
+        assert_eq!(name.get("ru"), Some(&"Кошка".to_string()));
+        // 回退链中的语言排在前面，其余按标签排序
+        assert_eq!(name.languages(), vec!["zh-hans", "en", "ja", "ru"]);
+        assert_eq!(name.resolve("ru-RU"), Some(&"Кошка".to_string()));
+        assert_eq!(name.resolve("zh-hant"), Some(&"猫".to_string()));
+        assert_eq!(name.resolve("ko"), Some(&"猫".to_string()));
+        assert_eq!(name.resolve_tag("zh-hans-cn"), Some(&"猫".to_string()));
+        assert_eq!(name.resolve_tag("ko"), None);
+    }
 }
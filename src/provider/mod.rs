@@ -5,23 +5,43 @@ pub mod manifest;
 pub use initializer::{init, reinit};
 
 use itertools::Itertools;
-use manifest::{Album, Manifest};
-use rand::Rng;
-use rand::seq::SliceRandom;
-use std::collections::HashSet;
+use manifest::{Album, FocalPoint, Manifest};
+use rand::seq::IndexedRandom;
+use std::collections::{BTreeSet, HashSet};
 use std::hash::{Hash, Hasher};
 use std::{
     collections::HashMap,
-    path::PathBuf,
-    sync::{OnceLock, RwLock, RwLockReadGuard},
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, RwLock},
 };
 
-use crate::errors::Result;
+use crate::errors::{Error, Result};
+use crate::models::params::AlbumFilter;
+use crate::vars::{CAPINDE_ALBUMS_BASE, CAPINDE_COLLECTIONS_BASE};
 use crate::{err, fail};
 
-static MANIFEST: OnceLock<RwLock<Manifest>> = OnceLock::new();
-static ALBUM_IMAGES: OnceLock<RwLock<HashMap<String, Vec<PathBuf>>>> = OnceLock::new();
-static CONFLICTS: OnceLock<RwLock<Conflicts>> = OnceLock::new();
+// 默认图集集合（位于 `CAPINDE_ALBUMS_BASE`），其它集合位于 `CAPINDE_COLLECTIONS_BASE` 的同名子目录
+pub const DEFAULT_COLLECTION: &str = "default";
+
+static COLLECTIONS: LazyLock<RwLock<HashMap<String, Arc<Collection>>>> =
+    LazyLock::new(Default::default);
+
+// 带标签的图片，标签总是包含所属图集的 ID
+#[derive(Debug, Clone)]
+pub struct TaggedImage {
+    pub path: PathBuf,
+    pub tags: BTreeSet<String>,
+}
+
+// 一组独立的图集：清单、图片、冲突和相似关系
+#[derive(Debug)]
+pub struct Collection {
+    pub manifest: Manifest,
+    album_images: HashMap<String, Vec<TaggedImage>>,
+    conflicts: Conflicts,
+    // 相似关系和冲突关系的结构相同（无序的图集对）
+    similarities: Conflicts,
+}
 
 #[derive(Debug, Clone)]
 struct ConflictPair(String, String);
@@ -77,136 +97,292 @@ impl Conflicts {
     }
 }
 
-pub fn get_manifest() -> Result<RwLockReadGuard<'static, Manifest>> {
-    let gurard = MANIFEST
-        .get()
-        .ok_or(fail!("manifest not initialized"))?
+/// 检查集合名称（仅允许小写字母、数字、下划线和短划线），返回集合的目录。
+pub fn collection_base(name: &str) -> Result<PathBuf> {
+    if name == DEFAULT_COLLECTION {
+        Ok(PathBuf::from(*CAPINDE_ALBUMS_BASE))
+    } else if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+    {
+        Ok(PathBuf::from(*CAPINDE_COLLECTIONS_BASE).join(name))
+    } else {
+        Err(Error::IllegalCollection(name.to_string()))
+    }
+}
+
+/// 获取已加载的集合，未指定名称时使用默认集合。
+pub fn get_collection(name: Option<&str>) -> Result<Arc<Collection>> {
+    let name = name.unwrap_or(DEFAULT_COLLECTION);
+    let collections = COLLECTIONS
         .read()
-        .map_err(|_| fail!("failed to get manifest"))?;
+        .map_err(|_| fail!("failed to read collections"))?;
 
-    Ok(gurard)
+    collections
+        .get(name)
+        .cloned()
+        .ok_or(Error::CollectionNotFound(name.to_string()))
 }
 
-fn reset_manifest(manifest: Manifest) -> Result<()> {
-    let mut guard = MANIFEST
-        .get()
-        .ok_or(fail!("manifest not initialized"))?
+fn reset_collection(name: &str, collection: Collection) -> Result<()> {
+    let mut collections = COLLECTIONS
         .write()
-        .map_err(|_| fail!("failed to get mutable manifest"))?;
-
-    *guard = manifest;
+        .map_err(|_| fail!("failed to get mutable collections"))?;
+    collections.insert(name.to_string(), Arc::new(collection));
 
     Ok(())
 }
 
-fn reset_album_images(album_images: HashMap<String, Vec<PathBuf>>) {
-    let mut guard = ALBUM_IMAGES
-        .get()
-        .expect("Album images not initialized")
-        .write()
-        .expect("Failed to get mutable album images");
-
-    *guard = album_images;
+// 移除已加载的集合（仅用于测试的清理）
+#[cfg(test)]
+pub fn remove_collection(name: &str) {
+    if let Ok(mut collections) = COLLECTIONS.write() {
+        collections.remove(name);
+    }
 }
 
-fn reset_conflicts(conflicts: Conflicts) {
-    let mut guard = CONFLICTS
-        .get()
-        .expect("Conflicts not initialized")
-        .write()
-        .expect("Failed to get mutable conflicts");
+impl Collection {
+    pub fn new(manifest: Manifest, album_images: HashMap<String, Vec<TaggedImage>>) -> Self {
+        let conflicts = Conflicts::from(manifest.conflicts.as_ref().unwrap_or(&vec![]));
+        let similarities = Conflicts::from(manifest.similarities.as_ref().unwrap_or(&vec![]));
 
-    *guard = conflicts;
-}
+        Self {
+            manifest,
+            album_images,
+            conflicts,
+            similarities,
+        }
+    }
 
-pub fn images_get(album_id: &str) -> Option<Vec<PathBuf>> {
-    let albums = ALBUM_IMAGES
-        .get()
-        .expect("Album images not initialized")
-        .read()
-        .expect("Failed to read album images");
+    /// 图集中不带有任何指定标签、且满足过滤条件的图片。
+    pub fn images_get_without(
+        &self,
+        album_id: &str,
+        tags: &[&str],
+        filter: &AlbumFilter,
+    ) -> Option<Vec<PathBuf>> {
+        if !filter.allows_album(album_id) {
+            return None;
+        }
 
-    albums.get(album_id).cloned()
-}
+        self.album_images.get(album_id).map(|images| {
+            images
+                .iter()
+                .filter(|image| !tags.iter().any(|tag| image.tags.contains(*tag)))
+                .filter(|image| filter.allows_image(&image.tags))
+                .map(|image| image.path.clone())
+                .collect()
+        })
+    }
 
-pub fn total_images() -> usize {
-    let albums = ALBUM_IMAGES
-        .get()
-        .expect("Album images not initialized")
-        .read()
-        .expect("Failed to read album images");
+    /// 所有带有指定标签、且满足过滤条件的图片（不限于同名图集）。
+    pub fn images_tagged(&self, tag: &str, filter: &AlbumFilter) -> Vec<PathBuf> {
+        self.album_images
+            .iter()
+            .filter(|(album_id, _)| filter.allows_album(album_id))
+            .flat_map(|(_, images)| images)
+            .filter(|image| image.tags.contains(tag) && filter.allows_image(&image.tags))
+            .map(|image| image.path.clone())
+            .collect()
+    }
 
-    albums.values().map(|v| v.len()).sum()
-}
+    pub fn is_tagged(&self, image: &Path, tag: &str) -> bool {
+        let Some(album_id) = image
+            .parent()
+            .and_then(|parent| parent.file_name())
+            .and_then(|name| name.to_str())
+        else {
+            return false;
+        };
 
-pub fn is_conflict(album1: &str, album2: &str) -> bool {
-    CONFLICTS
-        .get()
-        .expect("Conflicts not initialized")
-        .read()
-        .expect("Failed to read conflicts")
-        .contains(album1, album2)
-}
+        self.album_images.get(album_id).is_some_and(|images| {
+            images
+                .iter()
+                .any(|tagged| tagged.path == image && tagged.tags.contains(tag))
+        })
+    }
 
-pub fn random_right_with_wrongs(
-    right_min_children: usize,
-    total_albums: usize,
-) -> Result<(Album, Vec<Album>)> {
-    let manifest = get_manifest()?;
-    let mut albums = manifest.albums.iter().collect::<Vec<&Album>>();
-    let mut right = None;
-    let mut wrongs = vec![];
-    // 打乱图集列表
-    albums.shuffle(&mut rand::rng());
-    // 找出第一个包含 right_min_children 张图片的图集作为正确答案
-    for album in albums.iter() {
-        if let Some(images) = images_get(&album.id) {
-            if images.len() >= right_min_children {
-                right = Some((*album).clone());
-                break;
-            }
-        }
+    // 清单中为图片声明的焦点（图片位于以图集 ID 命名的目录中）
+    pub fn focal_point(&self, image: &Path) -> Option<FocalPoint> {
+        let album_id = image.parent()?.file_name()?.to_str()?;
+        let file_name = image.file_name()?.to_str()?;
+
+        self.manifest
+            .albums
+            .iter()
+            .find(|album| album.id == album_id)?
+            .focal_points
+            .as_ref()?
+            .get(file_name)
+            .copied()
     }
 
-    let right = if let Some(album) = right {
-        album
-    } else {
-        return err!("no album with enough images found");
-    };
+    pub fn total_images(&self) -> usize {
+        self.album_images.values().map(|v| v.len()).sum()
+    }
+
+    pub fn is_conflict(&self, album1: &str, album2: &str) -> bool {
+        self.conflicts.contains(album1, album2)
+    }
+
+    pub fn is_similar(&self, album1: &str, album2: &str) -> bool {
+        self.similarities.contains(album1, album2)
+    }
+
+    // 满足过滤条件的图集及其可用图片数量
+    fn eligible_albums(&self, filter: &AlbumFilter) -> Vec<(&Album, usize)> {
+        self.manifest
+            .albums
+            .iter()
+            .filter_map(|album| {
+                let count = self.images_get_without(&album.id, &[], filter)?.len();
+                (count > 0).then_some((album, count))
+            })
+            .collect()
+    }
+
+    /// 无论选中哪个图集作为正确答案，都能生成的最大候选项个数（正确答案加上无冲突的错误答案）。
+    pub fn max_choices(&self, filter: &AlbumFilter) -> usize {
+        let albums = self.eligible_albums(filter);
+
+        albums
+            .iter()
+            .map(|(right, _)| {
+                1 + albums
+                    .iter()
+                    .filter(|(album, _)| {
+                        album.id != right.id && !self.is_conflict(&right.id, &album.id)
+                    })
+                    .count()
+            })
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// 随机选择正确答案和错误答案，`hardness`（0.0 - 1.0）为优先从相似图集中选取错误答案的比例。
+    /// 只会选择满足过滤条件、且包含可用图片的图集。
+    pub fn random_right_with_wrongs(
+        &self,
+        right_min_children: usize,
+        total_albums: usize,
+        hardness: f64,
+        filter: &AlbumFilter,
+    ) -> Result<(Album, Vec<Album>)> {
+        self.pick_right_with_wrongs(right_min_children, total_albums - 1, hardness, filter, true)
+    }
 
-    // 从图集列表中选择特定数量无冲突的错误答案
-    while wrongs.len() < total_albums - 1 {
-        if albums.is_empty() {
-            return err!("not enough albums to generate wrongs");
+    /// 和 [`Collection::random_right_with_wrongs`] 类似，但无冲突的图集不足时返回尽可能多的错误答案。
+    pub fn random_right_with_at_most_wrongs(
+        &self,
+        right_min_children: usize,
+        max_wrongs: usize,
+        hardness: f64,
+        filter: &AlbumFilter,
+    ) -> Result<(Album, Vec<Album>)> {
+        let (right, wrongs) =
+            self.pick_right_with_wrongs(right_min_children, max_wrongs, hardness, filter, false)?;
+        if wrongs.is_empty() && max_wrongs > 0 {
+            return err!("no albums available to generate wrongs");
         }
-        // 随机选择一个图集
-        let random_index = rand::rng().random_range(0..albums.len());
-        let random = albums.remove(random_index);
-
-        if random.id == right.id {
-            // 如果生成了正确答案，继续下一个循环
-            continue;
-        } else if is_conflict(&right.id, &random.id) {
-            // 如果冲突，继续
-            continue;
-        } else if wrongs.iter().any(|a: &Album| a.id == random.id) {
-            // 如果已经存在于 wrongs 中，继续
-            continue;
+
+        Ok((right, wrongs))
+    }
+
+    fn pick_right_with_wrongs(
+        &self,
+        right_min_children: usize,
+        wrongs_count: usize,
+        hardness: f64,
+        filter: &AlbumFilter,
+        strict: bool,
+    ) -> Result<(Album, Vec<Album>)> {
+        let albums = self.eligible_albums(filter);
+        let mut wrongs = vec![];
+        // 按权重选择一个包含 right_min_children 张图片的图集作为正确答案
+        let candidates = albums
+            .iter()
+            .filter(|(_, count)| *count >= right_min_children)
+            .map(|(album, _)| *album)
+            .collect::<Vec<_>>();
+        let right = if let Some(album) = choose_weighted(&candidates) {
+            album.clone()
         } else {
-            // 没有冲突，添加到 wrongs
-            wrongs.push((*random).clone());
+            return err!("no album with enough images found");
+        };
+        let mut albums = albums
+            .into_iter()
+            .map(|(album, _)| album)
+            .collect::<Vec<_>>();
+
+        // 按比例优先选择与正确答案相似的图集
+        let similar_count = (wrongs_count as f64 * hardness.clamp(0.0, 1.0)).round() as usize;
+        let mut similar = albums
+            .iter()
+            .filter(|album| {
+                album.id != right.id
+                    && self.is_similar(&right.id, &album.id)
+                    && !self.is_conflict(&right.id, &album.id)
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        while wrongs.len() < similar_count
+            && let Some(album) = choose_weighted(&similar)
+        {
+            similar.retain(|a| a.id != album.id);
+            wrongs.push(album.clone());
         }
+
+        // 从图集列表中选择特定数量无冲突的错误答案
+        while wrongs.len() < wrongs_count {
+            // 按权重随机选择一个图集
+            let Some(random) = choose_weighted(&albums) else {
+                if strict {
+                    return err!("not enough albums to generate wrongs");
+                } else {
+                    break;
+                }
+            };
+            albums.retain(|a| a.id != random.id);
+
+            if random.id == right.id {
+                // 如果生成了正确答案，继续下一个循环
+                continue;
+            } else if self.is_conflict(&right.id, &random.id) {
+                // 如果冲突，继续
+                continue;
+            } else if wrongs.iter().any(|a: &Album| a.id == random.id) {
+                // 如果已经存在于 wrongs 中，继续
+                continue;
+            } else {
+                // 没有冲突，添加到 wrongs
+                wrongs.push((*random).clone());
+            }
+        }
+
+        Ok((right, wrongs))
     }
+}
 
-    Ok((right, wrongs))
+// 按权重随机选择一个图集，权重全部为 0 时均匀选择
+fn choose_weighted<'a>(albums: &[&'a Album]) -> Option<&'a Album> {
+    let mut rng = rand::rng();
+
+    albums
+        .choose_weighted(&mut rng, |album| album.weight())
+        .ok()
+        .or_else(|| albums.choose(&mut rng))
+        .copied()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn setup() {
+    fn setup() -> Arc<Collection> {
         super::init();
+
+        get_collection(None).unwrap()
     }
 
     #[test]
@@ -229,6 +405,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_collection_base() {
+        assert_eq!(
+            collection_base(DEFAULT_COLLECTION).unwrap(),
+            PathBuf::from(*CAPINDE_ALBUMS_BASE)
+        );
+        assert_eq!(
+            collection_base("birds_2-hd").unwrap(),
+            PathBuf::from(*CAPINDE_COLLECTIONS_BASE).join("birds_2-hd")
+        );
+        // 空名称、路径穿越、大写字母和非 ASCII 字符都是非法的
+        for name in ["", "..", "../albums", "a/b", "Birds", "鸟"] {
+            assert!(matches!(
+                collection_base(name),
+                Err(Error::IllegalCollection(n)) if n == name
+            ));
+        }
+    }
+
     #[test]
     fn test_conflicts() {
         setup();
@@ -252,9 +447,11 @@ mod tests {
 
     #[test]
     fn test_random_right_with_wrongs() {
-        setup();
+        let collection = setup();
 
-        let (right, wrongs) = random_right_with_wrongs(1, 9).unwrap();
+        let (right, wrongs) = collection
+            .random_right_with_wrongs(1, 9, 0.0, &AlbumFilter::default())
+            .unwrap();
         // 生成数量是否满足
         assert_eq!(wrongs.len(), 8);
 
@@ -263,7 +460,134 @@ mod tests {
 
         for other in wrongs.iter() {
             // 判断 right 是否不和 wrongs 中的任何一个冲突
-            assert!(!is_conflict(&right.id, &other.id));
+            assert!(!collection.is_conflict(&right.id, &other.id));
+        }
+    }
+
+    #[test]
+    fn test_tagged_images() {
+        let collection = setup();
+
+        let flower = PathBuf::from("tests/fixtures/albums/flowers/09a3106158f05b1.jpg");
+        let boat = PathBuf::from("tests/fixtures/albums/boats/00615098f8fc404.jpg");
+        // 清单和 `tags.yaml` 中的标签都会被加载
+        let no_filter = AlbumFilter::default();
+        let birds = collection.images_tagged("birds", &no_filter);
+        assert!(birds.contains(&flower));
+        assert!(birds.contains(&boat));
+        assert!(birds.len() > 2);
+        // 所属图集总是作为标签
+        assert!(collection.is_tagged(&flower, "flowers"));
+        assert!(collection.is_tagged(&flower, "birds"));
+        assert!(!collection.is_tagged(&flower, "cats"));
+
+        let flowers = collection
+            .images_get_without("flowers", &["birds"], &no_filter)
+            .unwrap();
+        assert!(!flowers.contains(&flower));
+        let all_flowers = collection
+            .images_get_without("flowers", &[], &no_filter)
+            .unwrap();
+        assert_eq!(flowers.len() + 1, all_flowers.len());
+    }
+
+    #[test]
+    fn test_album_filter() {
+        let collection = setup();
+
+        let filter = AlbumFilter {
+            include_albums: Some(vec![
+                "cats".to_string(),
+                "flowers".to_string(),
+                "boats".to_string(),
+                "birds".to_string(),
+            ]),
+            exclude_albums: Some(vec!["birds".to_string()]),
+            ..Default::default()
+        };
+        for _ in 0..10 {
+            let (right, wrongs) = collection
+                .random_right_with_wrongs(1, 3, 0.5, &filter)
+                .unwrap();
+            let ids = std::iter::once(&right)
+                .chain(&wrongs)
+                .map(|album| album.id.as_str())
+                .collect::<Vec<_>>();
+            assert!(
+                ids.iter()
+                    .all(|id| ["cats", "flowers", "boats"].contains(id))
+            );
+        }
+        // 带有被排除图集标签的图片也会被排除
+        let boats = collection
+            .images_get_without("boats", &[], &filter)
+            .unwrap();
+        assert!(!boats.contains(&PathBuf::from(
+            "tests/fixtures/albums/boats/00615098f8fc404.jpg"
+        )));
+        assert!(
+            collection
+                .images_get_without("dogs", &[], &filter)
+                .is_none()
+        );
+        assert!(collection.images_tagged("birds", &filter).is_empty());
+        // 没有满足条件的图集时返回错误
+        assert!(
+            collection
+                .random_right_with_wrongs(1, 4, 0.5, &filter)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_weighted_albums() {
+        let collection = setup();
+
+        for _ in 0..20 {
+            let (right, wrongs) = collection
+                .random_right_with_wrongs(1, 2, 0.0, &AlbumFilter::default())
+                .unwrap();
+            // 权重为 0 的图集不会被选中
+            assert_ne!(right.id, "zebras");
+            assert!(wrongs.iter().all(|a| a.id != "zebras"));
+        }
+        // 没有其它图集可选时仍会被选中
+        let (right, wrongs) = collection
+            .random_right_with_at_most_wrongs(1, 9, 0.0, &AlbumFilter::default())
+            .unwrap();
+        assert!(right.id == "zebras" || wrongs.iter().any(|a| a.id == "zebras"));
+    }
+
+    #[test]
+    fn test_max_choices() {
+        let collection = setup();
+
+        // 猫和狗冲突，选中其中之一时只剩下 8 个错误答案
+        assert_eq!(collection.max_choices(&AlbumFilter::default()), 9);
+        let filter = AlbumFilter {
+            include_albums: Some(vec!["cats".to_string(), "dogs".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(collection.max_choices(&filter), 1);
+    }
+
+    #[test]
+    fn test_similar_wrongs() {
+        let collection = setup();
+
+        for _ in 0..20 {
+            let (right, wrongs) = collection
+                .random_right_with_wrongs(1, 3, 1.0, &AlbumFilter::default())
+                .unwrap();
+            assert_eq!(wrongs.len(), 2);
+            // 相似图集总是优先被选为错误答案
+            let similar = ["airplanes", "birds", "coins", "watches"]
+                .into_iter()
+                .filter(|id| *id != right.id && collection.is_similar(&right.id, id))
+                .collect::<Vec<_>>();
+            for id in similar {
+                assert!(wrongs.iter().any(|a| a.id == id));
+            }
         }
     }
 }
@@ -0,0 +1,250 @@
+use crate::vars::{
+    COOLDOWN_FAILURES, COOLDOWN_SECS, MAX_REPUTATION_SUBJECTS, REPUTATION_WINDOW_SECS,
+};
+use log::{debug, info};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::LazyLock,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::RwLock;
+
+// 难度提升的最高等级
+const MAX_ESCALATION_LEVEL: usize = 3;
+// 达到上限时一次移除的终端用户比例
+const EVICTION_RATIO: f64 = 0.1;
+
+// (命名空间, 终端用户标识)
+type SubjectKey = (String, String);
+type Records = HashMap<SubjectKey, VecDeque<Record>>;
+static RECORDS: LazyLock<RwLock<Records>> = LazyLock::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy)]
+struct Record {
+    // 验证于
+    at: SystemTime,
+    // 是否通过
+    passed: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Standing {
+    // 时间窗口内的失败次数
+    pub failures: usize,
+    // 冷却剩余秒数
+    pub cooldown_secs: Option<u64>,
+}
+
+impl Standing {
+    // 难度提升等级
+    pub fn escalation_level(&self) -> usize {
+        self.failures.min(MAX_ESCALATION_LEVEL)
+    }
+}
+
+fn window() -> Duration {
+    Duration::from_secs(*REPUTATION_WINDOW_SECS)
+}
+
+// 移除时间窗口之外的记录
+fn prune(records: &mut VecDeque<Record>, now: SystemTime) {
+    while let Some(record) = records.front() {
+        if now.duration_since(record.at).unwrap_or_default() <= window() {
+            break;
+        }
+        records.pop_front();
+    }
+}
+
+fn evaluate(records: &VecDeque<Record>, now: SystemTime) -> Standing {
+    let failures = records.iter().filter(|r| !r.passed).count();
+    let cooldown_secs = if failures >= *COOLDOWN_FAILURES {
+        // 从最后一次失败开始冷却
+        records
+            .iter()
+            .rev()
+            .find(|r| !r.passed)
+            .map(|r| r.at + Duration::from_secs(*COOLDOWN_SECS))
+            .and_then(|until| until.duration_since(now).ok())
+            .map(|remaining| remaining.as_secs().max(1))
+    } else {
+        None
+    };
+
+    Standing {
+        failures,
+        cooldown_secs,
+    }
+}
+
+// 为新的终端用户腾出空间：先移除过期记录，仍然超出目标数量时移除最久未活动的一批终端用户。
+// 每次都腾出一批空间，避免达到上限后每个新的终端用户都触发全量扫描
+fn make_room(records: &mut Records, now: SystemTime, capacity: usize) {
+    if records.len() < capacity {
+        return;
+    }
+    records.retain(|_, subject_records| {
+        prune(subject_records, now);
+        !subject_records.is_empty()
+    });
+    let batch = ((capacity as f64 * EVICTION_RATIO).ceil() as usize).max(1);
+    let target = capacity.saturating_sub(batch);
+    if records.len() <= target {
+        return;
+    }
+    let excess = records.len() - target;
+    let mut idle = records
+        .iter()
+        .map(|(key, subject_records)| (subject_records.back().map(|r| r.at), key))
+        .collect::<Vec<_>>();
+    idle.select_nth_unstable_by_key(excess - 1, |(last_at, _)| *last_at);
+    let evicted = idle[..excess]
+        .iter()
+        .map(|(_, key)| (*key).clone())
+        .collect::<Vec<_>>();
+    for key in evicted {
+        records.remove(&key);
+    }
+}
+
+pub async fn record(namespace: &str, subject_id: &str, passed: bool) {
+    let now = SystemTime::now();
+    let mut records = RECORDS.write().await;
+    let key = (namespace.to_string(), subject_id.to_string());
+    if !records.contains_key(&key) {
+        make_room(&mut records, now, *MAX_REPUTATION_SUBJECTS);
+    }
+    let subject_records = records.entry(key).or_default();
+    prune(subject_records, now);
+    subject_records.push_back(Record { at: now, passed });
+}
+
+pub async fn standing(namespace: &str, subject_id: &str) -> Standing {
+    let now = SystemTime::now();
+    let records = RECORDS.read().await;
+    let key = (namespace.to_string(), subject_id.to_string());
+    match records.get(&key) {
+        Some(subject_records) => {
+            let mut subject_records = subject_records.clone();
+            prune(&mut subject_records, now);
+
+            evaluate(&subject_records, now)
+        }
+        None => Standing::default(),
+    }
+}
+
+// 清理时间窗口之外的记录
+pub async fn cleanup_expired() {
+    let now = SystemTime::now();
+    let mut records = RECORDS.write().await;
+    let before = records.len();
+    records.retain(|_, subject_records| {
+        prune(subject_records, now);
+        !subject_records.is_empty()
+    });
+    let removed_count = before - records.len();
+    if removed_count > 0 {
+        info!("Removed {removed_count} expired reputation record(s)");
+    } else {
+        debug!("No expired reputation records to remove");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(now: SystemTime, outcomes: &[(u64, bool)]) -> VecDeque<Record> {
+        outcomes
+            .iter()
+            .map(|(secs_ago, passed)| Record {
+                at: now - Duration::from_secs(*secs_ago),
+                passed: *passed,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let now = SystemTime::now();
+
+        // 没有失败
+        let standing = evaluate(&records(now, &[(30, true)]), now);
+        assert_eq!(standing, Standing::default());
+
+        // 失败次数决定难度等级，且等级有上限
+        let standing = evaluate(&records(now, &[(30, false), (20, true), (10, false)]), now);
+        assert_eq!(standing.failures, 2);
+        assert_eq!(standing.escalation_level(), 2);
+        assert_eq!(standing.cooldown_secs, None);
+
+        // 失败次数达到阈值，进入冷却
+        let outcomes = vec![(10, false); *COOLDOWN_FAILURES];
+        let standing = evaluate(&records(now, &outcomes), now);
+        assert_eq!(standing.escalation_level(), MAX_ESCALATION_LEVEL);
+        assert!(standing.cooldown_secs.is_some());
+
+        // 冷却时间已过
+        let outcomes = vec![(*COOLDOWN_SECS + 1, false); *COOLDOWN_FAILURES];
+        let standing = evaluate(&records(now, &outcomes), now);
+        assert_eq!(standing.cooldown_secs, None);
+    }
+
+    #[test]
+    fn test_make_room() {
+        let now = SystemTime::now();
+        let key = |subject_id: &str| ("test".to_string(), subject_id.to_string());
+        let mut subject_records: Records = [
+            (
+                key("expired"),
+                records(now, &[(*REPUTATION_WINDOW_SECS + 10, false)]),
+            ),
+            (key("idle"), records(now, &[(30, false)])),
+            (key("active"), records(now, &[(10, false)])),
+        ]
+        .into();
+
+        // 未达到上限时不做处理
+        make_room(&mut subject_records, now, 4);
+        assert_eq!(subject_records.len(), 3);
+        // 先移除过期记录
+        make_room(&mut subject_records, now, 3);
+        assert!(!subject_records.contains_key(&key("expired")));
+        assert_eq!(subject_records.len(), 2);
+        // 再移除最久未活动的终端用户
+        make_room(&mut subject_records, now, 2);
+        assert_eq!(
+            subject_records.keys().collect::<Vec<_>>(),
+            vec![&key("active")]
+        );
+
+        // 一次移除一批最久未活动的终端用户
+        let mut subject_records: Records = (0..20)
+            .map(|i| (key(&i.to_string()), records(now, &[(100 - i, false)])))
+            .collect();
+        make_room(&mut subject_records, now, 20);
+        assert_eq!(subject_records.len(), 18);
+        assert!(!subject_records.contains_key(&key("0")));
+        assert!(!subject_records.contains_key(&key("1")));
+        // 腾出空间后，新的终端用户不会再触发移除
+        make_room(&mut subject_records, now, 20);
+        assert_eq!(subject_records.len(), 18);
+    }
+
+    #[test]
+    fn test_prune() {
+        let now = SystemTime::now();
+        let mut subject_records = records(
+            now,
+            &[
+                (*REPUTATION_WINDOW_SECS + 10, false),
+                (10, false),
+                (5, true),
+            ],
+        );
+        prune(&mut subject_records, now);
+
+        assert_eq!(subject_records.len(), 2);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{
+    errors::{Error, Result},
+    fail, janitor,
+    models::{
+        params::challenge::Refresh,
+        payload::{ChallengeDetails, ChallengeStatus, Generated, Success},
+    },
+    reputation,
+    routes::generation::{
+        build_answer, build_generated, build_out_base, build_right_index, create,
+    },
+    verification::{self, Entry},
+};
+use axum::{Json, extract::Path};
+
+pub async fn status(Path(unique_id): Path<String>) -> Json<ChallengeStatus> {
+    let details = verification::get_cache(&unique_id)
+        .await
+        .map(|entry| ChallengeDetails {
+            kind: entry.kind,
+            namespace: entry.namespace,
+            created_at: entry.created_at.into(),
+            expires_at: entry.expires_at.into(),
+            attempts: entry.attempts,
+            outcome: entry.outcome,
+        });
+
+    Json(ChallengeStatus {
+        exists: details.is_some(),
+        details,
+    })
+}
+
+pub async fn revoke(Path(unique_id): Path<String>) -> Result<Json<Success>> {
+    discard(&unique_id)
+        .await?
+        .ok_or(Error::VerificationCacheNotFound(unique_id))?;
+
+    Ok(Json(Success::default()))
+}
+
+// 移除验证缓存，并立即删除输出文件（无需等待过期清理）
+pub async fn discard(unique_id: &str) -> Result<Option<Entry>> {
+    match verification::remove_cache(unique_id).await {
+        Some(entry) => {
+            janitor::discard_all(build_out_base(&entry.namespace)?, &entry.files).await?;
+
+            Ok(Some(entry))
+        }
+        None => Ok(None),
+    }
+}
+
+// 未绑定终端用户的验证可以不提供请求体
+pub async fn refresh(
+    Path(unique_id): Path<String>,
+    refresh: Option<Json<Refresh>>,
+) -> Result<Json<Generated>> {
+    let Json(refresh) = refresh.unwrap_or_default();
+    let entry = verification::get_cache(&unique_id)
+        .await
+        .ok_or(Error::VerificationCacheNotFound(unique_id.clone()))?;
+    // 只有生成时绑定的终端用户可以刷新
+    if !verification::is_same_subject(&entry, refresh.subject_id.as_deref()) {
+        return Err(Error::SubjectMismatch);
+    }
+    // 处于冷却期的终端用户不能通过刷新绕过冷却
+    if let Some(subject_id) = &entry.subject_id {
+        let standing = reputation::standing(&entry.namespace, subject_id).await;
+        if let Some(retry_after_secs) = standing.cooldown_secs {
+            return Err(Error::SubjectCoolingDown { retry_after_secs });
+        }
+    }
+    let ttl_secs = entry
+        .remaining_ttl_secs()
+        .ok_or(Error::VerificationCacheNotFound(unique_id.clone()))?;
+    // 使用原始参数重新生成
+    let input = entry
+        .input
+        .ok_or_else(|| fail!("challenge cannot be regenerated: {unique_id}"))?;
+    let out_base = build_out_base(&entry.namespace)?;
+    let (created, collection) = create(out_base.clone(), &input).await?;
+    let files = created.files();
+    let generated = match build_generated(&input, unique_id.clone(), created, collection.as_deref())
+    {
+        Ok(generated) => generated,
+        Err(e) => {
+            janitor::discard_all(out_base, &files).await?;
+            return Err(e);
+        }
+    };
+    let answer = build_answer(&input, &generated)?;
+    let right_index = build_right_index(&input, &generated);
+
+    match verification::refresh_cache(&unique_id, files.clone(), answer, right_index).await {
+        Some(replaced) => {
+            // 删除旧的输出文件，并将新文件添加到清理器（沿用剩余的 TTL）
+            janitor::discard_all(out_base.clone(), &replaced.files).await?;
+            janitor::collect_all(out_base, &files, ttl_secs).await;
+
+            Ok(Json(generated))
+        }
+        None => {
+            // 重新生成期间验证缓存已被移除
+            janitor::discard_all(out_base, &files).await?;
+
+            Err(Error::VerificationCacheNotFound(unique_id))
+        }
+    }
+}
@@ -1,73 +1,195 @@
 use crate::{
-    captchas::{classic, grid, idgen, image},
+    captchas::{Created, classic, grid, idgen, image, keyboard, prompt},
     errors::{Error, Result},
     janitor,
     models::{
         params::{Input, SpecialParams, verification::Answer},
         payload::Generated,
     },
+    provider::{self, Collection},
+    reputation,
     vars::{CAPINDE_NAMESPACE_BASE, CAPINDE_WORKING_MODE, MAX_TTL_SECS},
-    verification,
+    verification::{self, Entry},
 };
 use axum::Json;
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc};
 use tokio::task::spawn_blocking;
 
-pub async fn generate(input: Json<Input>) -> Result<Json<Generated>> {
+const FALLBACK_TTL_SECS: u64 = 60 * 15; // 后备过期时间：15 分钟
+
+pub async fn generate(Json(input): Json<Input>) -> Result<Json<Generated>> {
+    Ok(Json(generate_challenge(input).await?))
+}
+
+// 生成验证并添加到验证缓存和清理器
+pub async fn generate_challenge(mut input: Input) -> Result<Generated> {
     // 读取 TTL
-    let ttl_secs = input.ttl_secs.unwrap_or(60 * 15); // 后备过期时间：15 分钟
-    if ttl_secs > *MAX_TTL_SECS {
-        return Err(Error::TTLTooLarge {
-            max: *MAX_TTL_SECS,
-            provided: ttl_secs,
-        });
+    let ttl_secs = check_ttl(input.ttl_secs)?;
+    // 检查作答时间窗口
+    if let (Some(min), Some(max)) = (input.min_solve_ms, input.max_solve_ms)
+        && min > max
+    {
+        return Err(Error::InvalidSolveWindow { min, max });
+    }
+    // 检查输出选项
+    if let Some(output) = &input.output {
+        output.check()?;
+    }
+    // 根据终端用户的近期表现提升难度，或拒绝生成
+    if let Some(subject_id) = &input.subject_id {
+        let standing = reputation::standing(&input.namespace, subject_id).await;
+        if let Some(retry_after_secs) = standing.cooldown_secs {
+            return Err(Error::SubjectCoolingDown { retry_after_secs });
+        }
+        input.escalate(standing.escalation_level())?;
     }
     // 从命名空间生成输出目录
     let out_base = build_out_base(&input.namespace)?;
-    let out_dir = out_base.clone();
+    let (created, collection) = create(out_base.clone(), &input).await?;
+    let files = created.files();
+    let generated = match build_generated(&input, idgen(), created, collection.as_deref()) {
+        Ok(generated) => generated,
+        Err(e) => {
+            // 丢弃已生成的文件
+            janitor::discard_all(out_base, &files).await?;
+            return Err(e);
+        }
+    };
+    let answer = build_answer(&input, &generated)?;
+    let right_index = build_right_index(&input, &generated);
+
+    let entry = Entry::new(
+        input.special_params.kind(),
+        input.namespace.clone(),
+        files.clone(),
+        answer,
+    )
+    .solve_window(input.min_solve_ms, input.max_solve_ms)
+    .bind_subject(input.subject_id.clone())
+    .with_right_index(right_index)
+    .regenerable(input);
+    // 添加到验证缓存
+    verification::add_cache(generated.unique_id.clone(), entry, ttl_secs).await;
+    // 添加到清理器
+    janitor::collect_all(out_base, &files, ttl_secs).await;
+
+    Ok(generated)
+}
+
+// 根据参数生成验证图片，同时返回生成时使用的图集集合
+pub async fn create(out_base: String, input: &Input) -> Result<(Created, Option<Arc<Collection>>)> {
     let choices_control = input.choices_control.clone().unwrap_or_default();
+    let output = input.output.clone().unwrap_or_default();
+    let album_filter = input.album_filter.clone().unwrap_or_default();
 
     let created = match &input.special_params {
         SpecialParams::Grid(params) => {
             let params = params.clone();
-            spawn_blocking(move || grid::create(&out_base, &choices_control, &params)).await??
+            let collection = provider::get_collection(input.collection.as_deref())?;
+            let used = collection.clone();
+            let created = spawn_blocking(move || {
+                grid::create(
+                    &out_base,
+                    &choices_control,
+                    &output,
+                    &used,
+                    &album_filter,
+                    &params,
+                )
+            })
+            .await??;
+
+            (created, Some(collection))
         }
         SpecialParams::Image(params) => {
             let params = params.clone();
-            spawn_blocking(move || image::create(&out_base, &choices_control, &params)).await??
+            let collection = provider::get_collection(input.collection.as_deref())?;
+            let used = collection.clone();
+            let created = spawn_blocking(move || {
+                image::create(
+                    &out_base,
+                    &choices_control,
+                    &output,
+                    &used,
+                    &album_filter,
+                    &params,
+                )
+            })
+            .await??;
+
+            (created, Some(collection))
         }
         SpecialParams::Classic(params) => {
             let params = params.clone();
-            spawn_blocking(move || classic::create(&out_base, &choices_control, &params)).await??
+            let created = spawn_blocking(move || {
+                classic::create(&out_base, &choices_control, &output, &params)
+            })
+            .await??;
+
+            (created, None)
         }
     };
 
-    let generated = Generated {
+    Ok(created)
+}
+
+// 提示模板可以被生成时所用集合的清单覆盖
+pub fn build_generated(
+    input: &Input,
+    unique_id: String,
+    created: Created,
+    collection: Option<&Collection>,
+) -> Result<Generated> {
+    let keyboard = match &input.keyboard {
+        Some(control) => keyboard::build(&created.payload, control)?,
+        None => None,
+    };
+
+    Ok(Generated {
         working_mode: *CAPINDE_WORKING_MODE,
         namespace: input.namespace.clone(),
         file_name: created.file_name,
-        unique_id: idgen(),
+        unique_id,
         right_index: created.right_index,
+        prompt: prompt::render(
+            &created.payload,
+            collection.map(|collection| &collection.manifest),
+            input.prompt_langs.as_deref(),
+        ),
+        keyboard,
         special_payload: created.payload,
-    };
+    })
+}
 
-    let answer = if input.use_index.unwrap_or(false) {
-        Answer::Index {
+pub fn build_answer(input: &Input, generated: &Generated) -> Result<Answer> {
+    if input.use_index.unwrap_or(false) {
+        Ok(Answer::Index {
             value: generated.right_index,
-        }
+        })
     } else {
-        Answer::try_from((&input.special_params, &generated.special_payload))?
-    };
+        Answer::try_from((&input.special_params, &generated.special_payload))
+    }
+}
 
-    // 添加到验证缓存
-    verification::add_cache(generated.unique_id.clone(), answer, ttl_secs).await;
-    // 添加到清理器
-    janitor::collect(out_dir, &generated.file_name, ttl_secs).await;
+// 仅在生成了候选项（或使用旧的 `use_index`）时才能通过索引验证
+pub fn build_right_index(input: &Input, generated: &Generated) -> Option<usize> {
+    (generated.special_payload.has_choices() || input.use_index.unwrap_or(false))
+        .then_some(generated.right_index)
+}
 
-    Ok(Json(generated))
+pub fn check_ttl(ttl_secs: Option<u64>) -> Result<u64> {
+    let ttl_secs = ttl_secs.unwrap_or(FALLBACK_TTL_SECS);
+    if ttl_secs > *MAX_TTL_SECS {
+        Err(Error::TTLTooLarge {
+            max: *MAX_TTL_SECS,
+            provided: ttl_secs,
+        })
+    } else {
+        Ok(ttl_secs)
+    }
 }
 
-fn build_out_base(namespace: &str) -> Result<String> {
+pub fn build_out_base(namespace: &str) -> Result<String> {
     // 仅允许 namespace 包含字母、数字、下划线、短划线和斜杠
     if !namespace
         .chars()
@@ -1,7 +1,9 @@
+pub mod challenge;
 pub mod generation;
 pub mod janitor;
 pub mod provider;
 pub mod server;
+pub mod session;
 mod verification;
 
 pub use generation::generate;
@@ -2,37 +2,48 @@ use crate::{
     errors::{Error, Result},
     models::payload::{DeployedInfo, Success},
     provider::{
-        self,
+        self, DEFAULT_COLLECTION,
         archive::{self, ArchiveInfo},
-        get_manifest,
+        collection_base, get_collection,
     },
-    vars::{CAPINDE_ALBUMS_BASE, CAPINDE_UPLOADED_DIR},
+    vars::CAPINDE_UPLOADED_DIR,
+};
+use axum::{
+    Json,
+    extract::{Multipart, Path},
 };
-use axum::{Json, extract::Multipart};
 use std::path::PathBuf;
 
 const ARCHIVE_FIELD_NAME: &str = "archive";
 const OUTPUT_FILE_NAME: &str = "albums.zip";
+// 命名集合的压缩包上传到此子目录，以集合名称命名
+const COLLECTIONS_UPLOADED_DIR: &str = "collections";
 
-pub async fn deployed() -> Result<Json<DeployedInfo>> {
-    let manifest = get_manifest()?.to_owned();
-    let total_images = provider::total_images();
+// 以下路由同时挂载在 `/api/provider`（默认集合）和 `/api/provider/{collection}` 下
+pub async fn deployed(collection: Option<Path<String>>) -> Result<Json<DeployedInfo>> {
+    let name = collection_name(collection)?;
+    let collection = get_collection(Some(&name))?;
 
     Ok(Json(DeployedInfo {
-        manifest,
-        total_images,
+        collection: name,
+        manifest: collection.manifest.clone(),
+        total_images: collection.total_images(),
     }))
 }
 
-pub async fn upload(mut multipart: Multipart) -> Result<Json<ArchiveInfo>> {
+pub async fn upload(
+    collection: Option<Path<String>>,
+    mut multipart: Multipart,
+) -> Result<Json<ArchiveInfo>> {
+    let archive_file = uploaded_archive(&collection_name(collection)?);
     while let Some(field) = multipart.next_field().await.unwrap() {
         if field.name() == Some(ARCHIVE_FIELD_NAME) {
             let bytes = field.bytes().await?;
             // 检查上传目录是否存在，如果不存在则创建
-            let uploaded_dir = PathBuf::from(*CAPINDE_UPLOADED_DIR);
-            std::fs::create_dir_all(&uploaded_dir)?;
+            if let Some(uploaded_dir) = archive_file.parent() {
+                std::fs::create_dir_all(uploaded_dir)?;
+            }
             // 将上传的文件保存到上传目录
-            let archive_file = uploaded_dir.join(OUTPUT_FILE_NAME);
             std::fs::write(&archive_file, bytes)?;
             // 从压缩包中加载清单数据
             let info = archive::read_info(archive_file)?;
@@ -44,8 +55,8 @@ pub async fn upload(mut multipart: Multipart) -> Result<Json<ArchiveInfo>> {
     Err(Error::MissingField(ARCHIVE_FIELD_NAME.to_string()))
 }
 
-pub async fn get_uploaded() -> Result<Json<ArchiveInfo>> {
-    let archive_file = PathBuf::from(*CAPINDE_UPLOADED_DIR).join(OUTPUT_FILE_NAME);
+pub async fn get_uploaded(collection: Option<Path<String>>) -> Result<Json<ArchiveInfo>> {
+    let archive_file = uploaded_archive(&collection_name(collection)?);
     if archive_file.exists() {
         // 从压缩包中加载清单数据
         let info = archive::read_info(archive_file)?;
@@ -55,8 +66,8 @@ pub async fn get_uploaded() -> Result<Json<ArchiveInfo>> {
     }
 }
 
-pub async fn delete_uploaded() -> Result<Json<Success>> {
-    let archive_file = PathBuf::from(*CAPINDE_UPLOADED_DIR).join(OUTPUT_FILE_NAME);
+pub async fn delete_uploaded(collection: Option<Path<String>>) -> Result<Json<Success>> {
+    let archive_file = uploaded_archive(&collection_name(collection)?);
     if archive_file.exists() {
         // 删除上传的压缩包
         std::fs::remove_file(&archive_file)?;
@@ -66,20 +77,150 @@ pub async fn delete_uploaded() -> Result<Json<Success>> {
     }
 }
 
-pub async fn deploy() -> Result<Json<Success>> {
-    // 将压缩包部署到 albums 目录
-    archive::deyloy(
-        PathBuf::from(*CAPINDE_UPLOADED_DIR).join(OUTPUT_FILE_NAME),
-        PathBuf::from(*CAPINDE_ALBUMS_BASE),
-    )?;
-    // 重新初始化提供商
-    provider::reinit(PathBuf::from(*CAPINDE_ALBUMS_BASE))?;
+pub async fn deploy(collection: Option<Path<String>>) -> Result<Json<Success>> {
+    let name = collection_name(collection)?;
+    let archive_file = uploaded_archive(&name);
+    if !archive_file.exists() {
+        return Err(Error::NoUploadedArchive);
+    }
+    // 将压缩包部署到集合目录（只替换该集合）
+    let target_dir = collection_base(&name)?;
+    std::fs::create_dir_all(&target_dir)?;
+    archive::deyloy(archive_file, target_dir)?;
+    // 重新加载该集合
+    provider::reinit(&name)?;
 
     Ok(Json(Success::default()))
 }
 
-pub async fn reload() -> Result<Json<Success>> {
-    provider::reinit(PathBuf::from(*CAPINDE_ALBUMS_BASE))?;
+pub async fn reload(collection: Option<Path<String>>) -> Result<Json<Success>> {
+    provider::reinit(&collection_name(collection)?)?;
 
     Ok(Json(Success::default()))
 }
+
+// 路径中的集合名称（检查合法性），未提供时为默认集合
+fn collection_name(collection: Option<Path<String>>) -> Result<String> {
+    let name = collection.map_or(DEFAULT_COLLECTION.to_string(), |Path(name)| name);
+    collection_base(&name)?;
+
+    Ok(name)
+}
+
+fn uploaded_archive(name: &str) -> PathBuf {
+    let uploaded_dir = PathBuf::from(*CAPINDE_UPLOADED_DIR);
+    if name == DEFAULT_COLLECTION {
+        uploaded_dir.join(OUTPUT_FILE_NAME)
+    } else {
+        uploaded_dir
+            .join(COLLECTIONS_UPLOADED_DIR)
+            .join(format!("{name}.zip"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vars::test_base;
+    use std::{io::Write, sync::Arc};
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    const MANIFEST: &str = "version: 0.1.2\ndatetime: 1970-01-01T00:00:00Z\ninclude_formats: [jpg]\n\
+                            albums:\n  - id: cats\n    name:\n      en: Cat\n";
+
+    fn fixture_images(count: usize) -> Vec<PathBuf> {
+        let mut images = std::fs::read_dir("tests/fixtures/albums/cats")
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "jpg"))
+            .collect::<Vec<_>>();
+        images.sort();
+        images.truncate(count);
+
+        images
+    }
+
+    // 打包只包含 cats 图集的压缩包，作为命名集合的上传文件
+    fn upload_archive(name: &str, images: &[PathBuf]) {
+        let archive_file = uploaded_archive(name);
+        std::fs::create_dir_all(archive_file.parent().unwrap()).unwrap();
+        let mut zip = ZipWriter::new(std::fs::File::create(&archive_file).unwrap());
+        zip.start_file("Manifest.yaml", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(MANIFEST.as_bytes()).unwrap();
+        for image in images {
+            let file_name = image.file_name().unwrap().to_str().unwrap();
+            zip.start_file(format!("cats/{file_name}"), SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(&std::fs::read(image).unwrap()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    // 上传目录和集合目录都位于测试专用的临时目录中（见 `vars::test_base`），只有部署测试会写入
+    fn cleanup(name: &str) {
+        provider::remove_collection(name);
+        let _ = std::fs::remove_dir_all(test_base());
+    }
+
+    #[test]
+    fn test_deploy_and_reload() {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            const NAME: &str = "test-deploy";
+            provider::init();
+            let default = get_collection(None).unwrap();
+            let images = fixture_images(3);
+
+            // 部署命名集合后即可从 `CAPINDE_COLLECTIONS_BASE` 加载
+            upload_archive(NAME, &images[..2]);
+            let result = deploy(Some(Path(NAME.to_string()))).await;
+            let deployed = get_collection(Some(NAME));
+            // 重新加载会读取集合目录中的新图片
+            std::fs::copy(
+                &images[2],
+                collection_base(NAME)
+                    .unwrap()
+                    .join("cats")
+                    .join(images[2].file_name().unwrap()),
+            )
+            .unwrap();
+            let reloaded = reload(Some(Path(NAME.to_string()))).await;
+            let after_reload = get_collection(Some(NAME));
+            cleanup(NAME);
+
+            assert!(result.is_ok());
+            let deployed = deployed.unwrap();
+            assert_eq!(deployed.manifest.albums.len(), 1);
+            assert_eq!(deployed.total_images(), 2);
+            assert!(reloaded.is_ok());
+            assert_eq!(after_reload.unwrap().total_images(), 3);
+            // 其它集合不受影响
+            assert!(Arc::ptr_eq(&default, &get_collection(None).unwrap()));
+            // 清理后不再注册该集合，也不会在测试目录之外写入文件
+            assert!(matches!(
+                get_collection(Some(NAME)),
+                Err(Error::CollectionNotFound(_))
+            ));
+            assert!(collection_base(NAME).unwrap().starts_with(test_base()));
+            assert!(uploaded_archive(NAME).starts_with(test_base()));
+        });
+    }
+
+    #[test]
+    fn test_illegal_or_missing_collection() {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            assert!(matches!(
+                deploy(Some(Path("../albums".to_string()))).await,
+                Err(Error::IllegalCollection(_))
+            ));
+            assert!(matches!(
+                reload(Some(Path("Birds".to_string()))).await,
+                Err(Error::IllegalCollection(_))
+            ));
+            assert!(matches!(
+                reload(Some(Path("test-missing".to_string()))).await,
+                Err(Error::CollectionNotFound(_))
+            ));
+        });
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{
+    captchas::idgen,
+    errors::{Error, Result},
+    models::{
+        params::session::{Input, Verification},
+        payload::{FailureReason, Generated, SessionState, SessionStatus},
+    },
+    routes::{
+        challenge,
+        generation::{check_ttl, generate_challenge},
+    },
+    verification::{
+        self,
+        session::{self, Session},
+    },
+};
+use axum::{Json, extract::Path};
+
+pub async fn create(Json(input): Json<Input>) -> Result<Json<SessionState>> {
+    let ttl_secs = check_ttl(input.ttl_secs)?;
+    if input.rounds.is_empty() {
+        return Err(Error::InvalidSession(
+            "at least one round is required".to_string(),
+        ));
+    }
+    let pass_threshold = input.pass_threshold.unwrap_or(input.rounds.len());
+    if pass_threshold == 0 || pass_threshold > input.rounds.len() {
+        return Err(Error::InvalidSession(format!(
+            "pass threshold must be between 1 and {}, provided: {pass_threshold}",
+            input.rounds.len()
+        )));
+    }
+
+    let session_id = idgen();
+    let mut session = Session::new(input, pass_threshold, ttl_secs);
+    // 仅生成第一轮，后续轮次在上一轮验证后生成
+    let generated = generate_round(&session_id, &session).await?;
+    session.unique_id = Some(generated.unique_id.clone());
+    session::add_session(session_id.clone(), session.clone()).await;
+
+    Ok(Json(session.state(&session_id, None, Some(generated))))
+}
+
+pub async fn status(Path(session_id): Path<String>) -> Result<Json<SessionState>> {
+    let session = session::get_session(&session_id)
+        .await
+        .ok_or(Error::SessionNotFound(session_id.clone()))?;
+
+    Ok(Json(session.state(&session_id, None, None)))
+}
+
+pub async fn verify(
+    Path(session_id): Path<String>,
+    Json(input): Json<Verification>,
+) -> Result<Json<SessionState>> {
+    let mut session = session::get_session(&session_id)
+        .await
+        .ok_or(Error::SessionNotFound(session_id.clone()))?;
+    let unique_id = match (&session.status, &session.unique_id) {
+        (SessionStatus::Pending, Some(unique_id)) => unique_id.clone(),
+        // 会话已结束
+        _ => return Ok(Json(session.state(&session_id, None, None))),
+    };
+
+    let result = verification::verify(&unique_id, input.subject_id.as_deref(), &input.answer)
+        .await
+        .ok_or(Error::VerificationCacheNotFound(unique_id.clone()))?;
+    // 其它终端用户的作答和超出作答时间窗口的作答不计入轮次结果
+    match result.reason {
+        Some(FailureReason::SubjectMismatch) => return Err(Error::SubjectMismatch),
+        Some(FailureReason::TooFast | FailureReason::TooSlow) => {
+            return Ok(Json(session.state(&session_id, Some(result), None)));
+        }
+        _ => {}
+    }
+
+    // 先生成下一轮，生成失败时当前轮次保持不变
+    let generated = if session.advance(result.ok) {
+        Some(generate_round(&session_id, &session).await?)
+    } else {
+        None
+    };
+    // 每一轮只验证一次，验证后立即移除
+    if challenge::discard(&unique_id).await?.is_none() {
+        // 当前轮次已被其它请求处理，丢弃刚生成的下一轮
+        if let Some(generated) = &generated {
+            challenge::discard(&generated.unique_id).await?;
+        }
+        let session = session::get_session(&session_id)
+            .await
+            .ok_or(Error::SessionNotFound(session_id.clone()))?;
+
+        return Ok(Json(session.state(&session_id, None, None)));
+    }
+    session.unique_id = generated
+        .as_ref()
+        .map(|generated| generated.unique_id.clone());
+    session::update_session(&session_id, session.clone()).await;
+
+    Ok(Json(session.state(&session_id, Some(result), generated)))
+}
+
+async fn generate_round(session_id: &str, session: &Session) -> Result<Generated> {
+    let ttl_secs = session
+        .remaining_ttl_secs()
+        .ok_or(Error::SessionNotFound(session_id.to_string()))?;
+    let input = session
+        .round_input(ttl_secs)
+        .ok_or(Error::InvalidSession("no more rounds".to_string()))?;
+
+    generate_challenge(input).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::params::{self, verification::Answer},
+        verification::Entry,
+    };
+
+    // 无法生成的轮次（作答时间窗口无效）
+    fn invalid_round() -> params::Input {
+        params::Input {
+            min_solve_ms: Some(2000),
+            max_solve_ms: Some(1000),
+            ..params::Input::classic("")
+        }
+    }
+
+    async fn pending_session(session_id: &str, unique_id: &str, subject_id: Option<&str>) {
+        let entry = Entry::new(
+            "classic",
+            "test".to_string(),
+            vec![],
+            Answer::Index { value: 0 },
+        )
+        .bind_subject(subject_id.map(str::to_string))
+        .with_right_index(Some(0));
+        verification::add_cache(unique_id.to_string(), entry, 60).await;
+
+        let input = Input {
+            namespace: "test".to_string(),
+            ttl_secs: None,
+            subject_id: subject_id.map(str::to_string),
+            pass_threshold: None,
+            rounds: vec![invalid_round(); 2],
+        };
+        let mut session = Session::new(input, 1, 60);
+        session.unique_id = Some(unique_id.to_string());
+        session::add_session(session_id.to_string(), session).await;
+    }
+
+    fn answer(value: usize, subject_id: Option<&str>) -> Json<Verification> {
+        Json(Verification {
+            subject_id: subject_id.map(str::to_string),
+            answer: Answer::Index { value },
+        })
+    }
+
+    #[test]
+    fn test_verify_keeps_round_on_generation_failure() {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            pending_session("session-generation-failure", "round-1", None).await;
+
+            // 答错后需要进入下一轮，但下一轮生成失败
+            let result = verify(
+                Path("session-generation-failure".to_string()),
+                answer(1, None),
+            )
+            .await;
+            assert!(matches!(result, Err(Error::InvalidSolveWindow { .. })));
+
+            // 当前轮次保持不变，仍然可以继续验证
+            let session = session::get_session("session-generation-failure")
+                .await
+                .unwrap();
+            assert_eq!(session.status, SessionStatus::Pending);
+            assert_eq!(session.round, 0);
+            assert_eq!(session.unique_id.as_deref(), Some("round-1"));
+            assert!(verification::get_cache("round-1").await.is_some());
+        });
+    }
+
+    #[test]
+    fn test_verify_ignores_other_subjects() {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            pending_session("session-other-subject", "round-2", Some("10086")).await;
+
+            // 其它终端用户的作答不会消耗轮次
+            let result = verify(
+                Path("session-other-subject".to_string()),
+                answer(1, Some("10010")),
+            )
+            .await;
+            assert!(matches!(result, Err(Error::SubjectMismatch)));
+
+            let session = session::get_session("session-other-subject").await.unwrap();
+            assert_eq!(session.failures, 0);
+            assert_eq!(session.unique_id.as_deref(), Some("round-2"));
+            assert!(verification::get_cache("round-2").await.is_some());
+        });
+    }
+}
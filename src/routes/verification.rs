@@ -6,8 +6,8 @@ use crate::{
 use axum::Json;
 
 pub async fn verify(input: Json<Input>) -> Result<Json<VefifyResult>> {
-    match verification::verify(&input.unique_id, &input.answer).await {
-        Some(ok) => Ok(Json(VefifyResult { ok })),
+    match verification::verify(&input.unique_id, input.subject_id.as_deref(), &input.answer).await {
+        Some(result) => Ok(Json(result)),
         None => Err(Error::VerificationCacheNotFound(input.unique_id.clone())),
     }
 }
@@ -1,4 +1,4 @@
-use crate::{errors::Result, fail, janitor, verification};
+use crate::{errors::Result, fail, janitor, reputation, verification};
 use chrono::{DateTime, Utc};
 use log::info;
 use std::{
@@ -61,6 +61,7 @@ impl MyScheduler {
 static SCHEDULER: OnceLock<RwLock<MyScheduler>> = OnceLock::new();
 
 const EVERY_15_SECONDS: &str = "*/15 * * * * *"; // 每 15 秒执行一次
+const EVERY_1_MINUTE: &str = "0 */1 * * * *"; // 每分钟执行一次
 const EVERY_1_HOUR: &str = "0 0 */1 * * *"; // 每小时执行一次
 
 async fn init_scheduler() -> Result<()> {
@@ -71,11 +72,13 @@ async fn init_scheduler() -> Result<()> {
     let fallback_janitor_cleanup = Task::new(|| async move { janitor::fallback_cleanup().await }); // 验证图片备用清理任务
     let ttl_verification_cleanup =
         Task::new(|| async move { verification::cleanup_expired().await }); // 验证图片 TTL 验证清理任务
+    let reputation_cleanup = Task::new(|| async move { reputation::cleanup_expired().await }); // 用户信誉记录清理任务
 
     // 从任务中创建定时作业
     let ttl_janitor_cleanup_job = ttl_janitor_cleanup.create_job(EVERY_15_SECONDS)?;
     let fallback_janitor_cleanup_job = fallback_janitor_cleanup.create_job(EVERY_1_HOUR)?;
     let ttl_verification_cleanup_job = ttl_verification_cleanup.create_job(EVERY_15_SECONDS)?;
+    let reputation_cleanup_job = reputation_cleanup.create_job(EVERY_1_MINUTE)?;
 
     SCHEDULER
         .set(RwLock::new(MyScheduler {
@@ -95,6 +98,9 @@ async fn init_scheduler() -> Result<()> {
     schedule.sched.add(ttl_verification_cleanup_job).await?;
     info!("Verification caches cleaner scheduled to run every 15 seconds");
 
+    schedule.sched.add(reputation_cleanup_job).await?;
+    info!("Reputation records cleaner scheduled to run every 1 minute");
+
     // Feature 'signal' must be enabled
     schedule.sched.shutdown_on_ctrl_c();
 
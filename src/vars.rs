@@ -22,11 +22,19 @@ env_config!(HOST, "localhost");
 env_config!(PORT, "8080");
 env_config!(WORKING_MODE, "hosted");
 env_config!(NAMESPACE_BASE, "namespace");
+#[cfg(not(test))]
 env_config!(UPLOADED_DIR, "uploaded");
 env_config!(ALBUMS_BASE, "albums");
+#[cfg(not(test))]
+env_config!(COLLECTIONS_BASE, "collections");
 env_config!(MAX_TTL_HOURS, "12");
 env_config!(MAX_UPLOAD_SIZE_MB, "300");
 env_config!(API_KEY, "");
+env_config!(REPUTATION_WINDOW_SECS, "3600");
+env_config!(COOLDOWN_FAILURES, "5");
+env_config!(COOLDOWN_SECS, "600");
+env_config!(MAX_REPUTATION_SUBJECTS, "100000");
+env_config!(LANG_FALLBACKS, "zh-hans,zh-hant,en");
 
 pub static STARTED_AT: OnceLock<chrono::DateTime<Utc>> = OnceLock::new();
 pub fn init_started_at() {
@@ -49,3 +57,59 @@ pub static MAX_UPLOAD_SIZE: LazyLock<usize> = LazyLock::new(|| {
         * 1024
         * 1024 // Convert MB to bytes
 });
+
+pub static REPUTATION_WINDOW_SECS: LazyLock<u64> = LazyLock::new(|| {
+    CAPINDE_REPUTATION_WINDOW_SECS
+        .parse::<u64>()
+        .expect("Invalid CAPINDE_REPUTATION_WINDOW_SECS value")
+});
+
+pub static COOLDOWN_FAILURES: LazyLock<usize> = LazyLock::new(|| {
+    CAPINDE_COOLDOWN_FAILURES
+        .parse::<usize>()
+        .expect("Invalid CAPINDE_COOLDOWN_FAILURES value")
+});
+
+pub static COOLDOWN_SECS: LazyLock<u64> = LazyLock::new(|| {
+    CAPINDE_COOLDOWN_SECS
+        .parse::<u64>()
+        .expect("Invalid CAPINDE_COOLDOWN_SECS value")
+});
+
+pub static MAX_REPUTATION_SUBJECTS: LazyLock<usize> = LazyLock::new(|| {
+    CAPINDE_MAX_REPUTATION_SUBJECTS
+        .parse::<usize>()
+        .expect("Invalid CAPINDE_MAX_REPUTATION_SUBJECTS value")
+});
+
+// 语言回退链（BCP-47 标签，按优先级排列）
+pub static LANG_FALLBACKS: LazyLock<Vec<String>> = LazyLock::new(|| {
+    CAPINDE_LANG_FALLBACKS
+        .split(',')
+        .map(|lang| lang.trim().replace('_', "-").to_ascii_lowercase())
+        .filter(|lang| !lang.is_empty())
+        .collect()
+});
+
+// 测试时上传目录和集合目录位于当前进程独有的临时目录中，避免读写真实的数据目录
+#[cfg(test)]
+pub static CAPINDE_UPLOADED_DIR: LazyLock<&'static str> = LazyLock::new(|| test_dir("uploaded"));
+#[cfg(test)]
+pub static CAPINDE_COLLECTIONS_BASE: LazyLock<&'static str> =
+    LazyLock::new(|| test_dir("collections"));
+
+#[cfg(test)]
+pub fn test_base() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("capinde-test-{}", std::process::id()))
+}
+
+#[cfg(test)]
+fn test_dir(name: &str) -> &'static str {
+    Box::leak(
+        test_base()
+            .join(name)
+            .to_string_lossy()
+            .into_owned()
+            .into_boxed_str(),
+    )
+}
@@ -1,17 +1,76 @@
+mod natural;
+pub mod session;
 mod store;
 
 use log::warn;
-pub use store::{add_cache, cleanup_expired, queue_size};
+pub use store::{Entry, add_cache, get_cache, queue_size, refresh_cache, remove_cache};
 
-use crate::models::params::verification::Answer::{self, Caassic, Grid, Image, Index};
+use crate::{
+    models::{
+        params::verification::Answer::{self, Caassic, Grid, Image, Index, Text},
+        payload::{FailureReason, VefifyResult},
+    },
+    reputation,
+};
 
-pub async fn verify(unique_id: &str, answer: &Answer) -> Option<bool> {
-    let cached = match store::get_cache(unique_id).await {
-        Some(cached_answer) => cached_answer,
-        None => return None,
+// 清理已过期的验证缓存和会话
+pub async fn cleanup_expired() {
+    store::cleanup_expired().await;
+    session::cleanup_expired().await;
+}
+
+pub async fn verify(
+    unique_id: &str,
+    subject_id: Option<&str>,
+    answer: &Answer,
+) -> Option<VefifyResult> {
+    let entry = store::get_cache(unique_id).await?;
+
+    // 检查终端用户是否为生成时绑定的用户
+    if !is_same_subject(&entry, subject_id) {
+        return Some(VefifyResult::failed(FailureReason::SubjectMismatch));
+    }
+    // 检查作答时间是否在允许的范围内
+    let solve_time_failure = check_solve_time(&entry, entry.elapsed_ms());
+    let counted = solve_time_failure.is_none();
+    let result = if let Some(reason) = solve_time_failure {
+        VefifyResult::failed(reason)
+    } else if is_right(&entry, answer) {
+        VefifyResult::passed()
+    } else {
+        VefifyResult::failed(FailureReason::WrongAnswer)
     };
+    store::record_attempt(unique_id, &result).await;
+    // 记录终端用户的验证结果（和会话轮次一致，超出作答时间窗口的作答不计入）
+    if counted && let Some(subject_id) = &entry.subject_id {
+        reputation::record(&entry.namespace, subject_id, result.ok).await;
+    }
+
+    Some(result)
+}
+
+pub fn is_same_subject(entry: &Entry, subject_id: Option<&str>) -> bool {
+    match &entry.subject_id {
+        // 生成时绑定了用户，则必须提供相同的用户
+        Some(bound) => subject_id == Some(bound.as_str()),
+        // 未绑定用户，不做检查
+        None => true,
+    }
+}
+
+fn check_solve_time(entry: &Entry, elapsed_ms: u64) -> Option<FailureReason> {
+    if entry.min_solve_ms.is_some_and(|min| elapsed_ms < min) {
+        Some(FailureReason::TooFast)
+    } else if entry.max_solve_ms.is_some_and(|max| elapsed_ms > max) {
+        Some(FailureReason::TooSlow)
+    } else {
+        None
+    }
+}
 
-    let is_right = match (&cached, answer) {
+fn is_right(entry: &Entry, answer: &Answer) -> bool {
+    let cached = &entry.answer;
+    match (cached, answer) {
         (Caassic(cached), Caassic(answer)) => {
             let ignore_case = if let Some(required_ignore_case) = answer.ignore_case {
                 required_ignore_case
@@ -48,24 +107,179 @@ pub async fn verify(unique_id: &str, answer: &Answer) -> Option<bool> {
             }
         }
         (Image(cached), Image(answer)) => {
-            // 按照简体、繁体、英文的顺序，某个语言答案存在就比较该语言答案
-            if answer.zh_hans.is_some() && cached.zh_hans.is_some() {
-                answer.zh_hans == cached.zh_hans
-            } else if answer.zh_hant.is_some() && cached.zh_hant.is_some() {
-                answer.zh_hant == cached.zh_hant
-            } else if answer.en.is_some() && cached.en.is_some() {
-                answer.en == cached.en
-            } else {
-                false
+            // 按照语言回退链的顺序，某个语言答案存在就比较该语言答案（或任一别名）
+            match answer
+                .name
+                .languages()
+                .into_iter()
+                .find_map(|lang| answer.name.get(lang).zip(cached.name.get(lang)))
+            {
+                Some((answer, name)) => natural::is_same_name(answer, name, &cached.aliases),
+                None => false,
             }
         }
-        (Index { value: right }, Index { value: answer }) => right == answer,
+        // 答案同时以索引形式存储，任何类型都可以通过索引验证
+        (_, Index { value }) => entry.right_index == Some(*value),
+        (_, Text { value }) => natural::matches(entry, value),
         _ => {
             warn!("Cached answer type mismatch: expected {cached:?}, got {answer:?}");
 
             false
         }
-    };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::params::{self, verification::Classic};
+
+    fn new_entry() -> Entry {
+        Entry::new(
+            "classic",
+            "test".to_string(),
+            vec!["test.jpg".to_string()],
+            Caassic(Classic {
+                text: "abcd".to_string(),
+                ignore_case: None,
+            }),
+        )
+        .with_right_index(Some(2))
+    }
+
+    #[test]
+    fn test_check_solve_time() {
+        let entry = new_entry().solve_window(Some(1000), Some(5000));
+
+        assert_eq!(check_solve_time(&entry, 200), Some(FailureReason::TooFast));
+        assert_eq!(check_solve_time(&entry, 1000), None);
+        assert_eq!(check_solve_time(&entry, 5000), None);
+        assert_eq!(check_solve_time(&entry, 5001), Some(FailureReason::TooSlow));
+
+        // 未设置时间窗口时不做限制
+        let entry = new_entry();
+        assert_eq!(check_solve_time(&entry, 0), None);
+        assert_eq!(check_solve_time(&entry, u64::MAX), None);
+    }
+
+    #[test]
+    fn test_verify_outside_solve_window() {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let entry = new_entry()
+                .solve_window(Some(60_000), None)
+                .bind_subject(Some("too-fast".to_string()));
+            add_cache("outside-solve-window".to_string(), entry, 60).await;
+
+            let result = verify(
+                "outside-solve-window",
+                Some("too-fast"),
+                &Index { value: 1 },
+            )
+            .await
+            .unwrap();
+            assert_eq!(result.reason, Some(FailureReason::TooFast));
+            // 超出作答时间窗口的作答不计入终端用户的失败次数
+            let standing = reputation::standing("test", "too-fast").await;
+            assert_eq!(standing.failures, 0);
+        });
+    }
+
+    #[test]
+    fn test_is_right() {
+        let entry = new_entry();
+
+        // 同一验证既可以通过原生答案，也可以通过索引验证
+        assert!(is_right(
+            &entry,
+            &Caassic(Classic {
+                text: "abcd".to_string(),
+                ignore_case: None
+            })
+        ));
+        assert!(is_right(&entry, &Index { value: 2 }));
+        assert!(is_right(
+            &entry,
+            &Text {
+                value: " abcd ".to_string()
+            }
+        ));
+        assert!(is_right(
+            &entry,
+            &Text {
+                value: "2".to_string()
+            }
+        ));
+        assert!(!is_right(&entry, &Index { value: 1 }));
+        assert!(!is_right(
+            &entry,
+            &Text {
+                value: "1".to_string()
+            }
+        ));
+
+        // 未生成候选项时不能通过索引验证
+        let mut entry = entry;
+        entry.right_index = None;
+        assert!(!is_right(&entry, &Index { value: 2 }));
+    }
+
+    #[test]
+    fn test_is_right_grid_text() {
+        let mut entry = new_entry().with_right_index(Some(3));
+        entry.answer = Grid(params::verification::Grid {
+            parts: vec![1, 5],
+            unordered: None,
+        });
+        let text = |value: &str| Text {
+            value: value.to_string(),
+        };
+
+        assert!(is_right(&entry, &text("5 1")));
+        // 网格的数字文本表示单元格编号，不能作为候选项索引
+        assert!(!is_right(&entry, &text("3")));
+        assert!(is_right(&entry, &Index { value: 3 }));
+    }
+
+    #[test]
+    fn test_is_right_image() {
+        let mut entry = new_entry();
+        entry.answer = serde_json::from_value(serde_json::json!({
+            "type": "image", "zh-hans": "猫", "en": "Cat", "ja": "ネコ"
+        }))
+        .unwrap();
+        let answer = |value| serde_json::from_value::<Answer>(value).unwrap();
+
+        // 兼容旧的语言键
+        assert!(is_right(
+            &entry,
+            &answer(serde_json::json!({ "type": "image", "zh_hans": "猫", "en": null }))
+        ));
+        assert!(is_right(
+            &entry,
+            &answer(serde_json::json!({ "type": "image", "ja": "ネコ" }))
+        ));
+        assert!(!is_right(
+            &entry,
+            &answer(serde_json::json!({ "type": "image", "en": "Dog" }))
+        ));
+        // 缓存中不存在的语言
+        assert!(!is_right(
+            &entry,
+            &answer(serde_json::json!({ "type": "image", "ru": "Кошка" }))
+        ));
+    }
+
+    #[test]
+    fn test_is_same_subject() {
+        let entry = new_entry().bind_subject(Some("10086".to_string()));
+
+        assert!(is_same_subject(&entry, Some("10086")));
+        assert!(!is_same_subject(&entry, Some("10010")));
+        assert!(!is_same_subject(&entry, None));
 
-    Some(is_right)
+        // 未绑定用户时，任何用户都可以验证
+        let entry = new_entry();
+        assert!(is_same_subject(&entry, None));
+        assert!(is_same_subject(&entry, Some("10086")));
+    }
 }
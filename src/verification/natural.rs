@@ -0,0 +1,233 @@
+//! 将终端用户输入的原始文本映射到缓存的答案上。
+
+use super::Entry;
+use crate::{
+    captchas::grid,
+    models::params::{
+        SpecialParams,
+        verification::Answer::{Caassic, Grid, Image, Index, Text},
+    },
+};
+use caseless::default_case_fold_str;
+use unicode_normalization::UnicodeNormalization;
+
+// 文本既可以是原生答案，也可以是候选项的索引
+// 网格的数字文本总是表示单元格编号，不作为候选项的索引
+pub fn matches(entry: &Entry, text: &str) -> bool {
+    matches_native(entry, text)
+        || (!matches!(entry.answer, Grid(_))
+            && entry
+                .right_index
+                .is_some_and(|index| text.trim().parse::<usize>() == Ok(index)))
+}
+
+fn matches_native(entry: &Entry, text: &str) -> bool {
+    match &entry.answer {
+        Caassic(cached) => {
+            let text = text.trim();
+            if cached.ignore_case.unwrap_or(false) {
+                text.eq_ignore_ascii_case(&cached.text)
+            } else {
+                text == cached.text
+            }
+        }
+        Grid(cached) => {
+            let (cells, labels) = match entry.input.as_ref().map(|input| &input.special_params) {
+                Some(SpecialParams::Grid(params)) => (
+                    grid::layout_cells(&params.layout),
+                    grid::cell_labels(params).unwrap_or_default(),
+                ),
+                _ => (None, vec![]),
+            };
+            // 既可以输入编号，也可以输入单元格标签
+            match parse_parts(text, cells).or_else(|| parse_labels(text, &labels)) {
+                Some(parts) => {
+                    // 除非明确要求有序，否则文本输入不考虑顺序
+                    let unordered = cached.unordered.unwrap_or(true);
+                    let mut right_parts = cached.parts.clone();
+                    let mut answer_parts = parts;
+                    if unordered {
+                        right_parts.sort_unstable();
+                        answer_parts.sort_unstable();
+                    }
+
+                    right_parts == answer_parts
+                }
+                None => false,
+            }
+        }
+        Image(cached) => {
+            let text = normalize(text);
+            cached
+                .name
+                .names()
+                .chain(&cached.aliases)
+                .any(|name| normalize(name) == text)
+        }
+        // 索引由 `right_index` 处理
+        Index { .. } | Text { .. } => false,
+    }
+}
+
+/// 解析网格编号，支持 `1 6 9`、`1,6,9` 和 `169` 等形式。
+/// 连写的数字仅在单元格不超过 9 个时按单个数字拆分。
+fn parse_parts(text: &str, cells: Option<usize>) -> Option<Vec<usize>> {
+    let cells = cells.unwrap_or(9);
+    let tokens = text
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|token| !token.is_empty())
+        .collect::<Vec<_>>();
+    // 除分隔符外只允许数字
+    if tokens.is_empty()
+        || text
+            .chars()
+            .any(|c| !c.is_ascii_digit() && !is_separator(c))
+    {
+        return None;
+    }
+
+    let parts = if tokens.len() == 1 && cells <= 9 {
+        tokens[0]
+            .chars()
+            .map(|c| c.to_digit(10).map(|d| d as usize))
+            .collect::<Option<Vec<_>>>()?
+    } else {
+        tokens
+            .iter()
+            .map(|token| token.parse::<usize>().ok())
+            .collect::<Option<Vec<_>>>()?
+    };
+
+    if parts.iter().all(|part| (1..=cells).contains(part)) {
+        Some(parts)
+    } else {
+        None
+    }
+}
+
+/// 解析单元格标签，支持 `A C F` 和 `acf` 等形式。
+/// 连写仅在所有标签都是单个字符时按字符拆分。
+fn parse_labels(text: &str, labels: &[String]) -> Option<Vec<usize>> {
+    let labels = labels
+        .iter()
+        .map(|label| normalize(label))
+        .collect::<Vec<_>>();
+    let find = |token: &str| {
+        let token = normalize(token);
+        labels
+            .iter()
+            .position(|label| *label == token)
+            .map(|i| i + 1)
+    };
+    let tokens = text
+        .split(is_separator)
+        .filter(|token| !token.is_empty())
+        .collect::<Vec<_>>();
+    if tokens.is_empty() {
+        return None;
+    }
+
+    let parts = tokens
+        .iter()
+        .map(|token| find(token))
+        .collect::<Option<Vec<_>>>();
+    if parts.is_none() && tokens.len() == 1 && labels.iter().all(|label| label.chars().count() == 1)
+    {
+        tokens[0]
+            .chars()
+            .map(|c| find(&c.to_string()))
+            .collect::<Option<Vec<_>>>()
+    } else {
+        parts
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, ',' | '，' | '、' | ';' | '；' | '/' | '-' | '.')
+}
+
+// 比较名称，名称的任一别名也视为相同
+pub fn is_same_name(answer: &str, name: &str, aliases: &[String]) -> bool {
+    let answer = normalize(answer);
+
+    std::iter::once(name)
+        .chain(aliases.iter().map(String::as_str))
+        .any(|name| normalize(name) == answer)
+}
+
+// 规范化文本：NFKC 规范化（合并组合字符、全角转半角等兼容形式）、大小写折叠，并忽略空白
+// 大小写折叠可能产生未规范化的字符，因此折叠后再规范化一次
+fn normalize(text: &str) -> String {
+    let text = text
+        .nfkc()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>();
+
+    default_case_fold_str(&text).nfkc().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::captchas::label::Scheme;
+
+    #[test]
+    fn test_parse_parts() {
+        assert_eq!(parse_parts("1 6 9", Some(9)), Some(vec![1, 6, 9]));
+        assert_eq!(parse_parts("169", Some(9)), Some(vec![1, 6, 9]));
+        assert_eq!(parse_parts("1,6,9", Some(9)), Some(vec![1, 6, 9]));
+        assert_eq!(parse_parts(" 1，6、9 ", Some(9)), Some(vec![1, 6, 9]));
+        // 超过 9 个单元格时必须使用分隔符
+        assert_eq!(parse_parts("1 12 16", Some(16)), Some(vec![1, 12, 16]));
+        assert_eq!(parse_parts("11216", Some(16)), None);
+        // 超出范围或包含其它字符
+        assert_eq!(parse_parts("0 6 9", Some(9)), None);
+        assert_eq!(parse_parts("1 6 10", Some(9)), None);
+        assert_eq!(parse_parts("1 and 6", Some(9)), None);
+        assert_eq!(parse_parts("", Some(9)), None);
+    }
+
+    #[test]
+    fn test_parse_labels() {
+        let letters = Scheme::Letters.labels(9);
+        assert_eq!(parse_labels("A F I", &letters), Some(vec![1, 6, 9]));
+        assert_eq!(parse_labels("afi", &letters), Some(vec![1, 6, 9]));
+        assert_eq!(parse_labels("Ａ，ｆ", &letters), Some(vec![1, 6]));
+        assert_eq!(parse_labels("A J", &letters), None);
+
+        let cjk = Scheme::Cjk.labels(16);
+        assert_eq!(parse_labels("一 十二 十六", &cjk), Some(vec![1, 12, 16]));
+        assert_eq!(parse_labels("一十二", &cjk), None);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(" Cat "), "cat");
+        assert_eq!(normalize("Coffee  Cup"), "coffeecup");
+        assert_eq!(normalize("ＫＩＴＴＹ"), "kitty");
+        assert_eq!(normalize("Ωμέγα"), "ωμέγα");
+        assert_eq!(normalize("猫\u{3000}咪"), "猫咪");
+        // 组合形式和分解形式相同
+        assert_eq!(normalize("Café"), normalize("Cafe\u{301}"));
+        assert_eq!(normalize("CAFE\u{301}"), "café");
+        // 兼容形式和大小写折叠
+        assert_eq!(normalize("ﬁsh"), "fish");
+        assert_eq!(normalize("Straße"), "strasse");
+        assert_eq!(normalize("ｶﾀｶﾅ"), "カタカナ");
+    }
+
+    #[test]
+    fn test_is_same_name() {
+        let aliases = vec!["Kitty".to_string(), "猫咪".to_string()];
+
+        assert!(is_same_name("cat", "Cat", &aliases));
+        assert!(is_same_name(" kitty", "Cat", &aliases));
+        assert!(is_same_name("猫咪", "猫", &aliases));
+        assert!(is_same_name(
+            "CRE\u{300}ME BRU\u{302}LE\u{301}E",
+            "Crème brûlée",
+            &[]
+        ));
+        assert!(!is_same_name("dog", "Cat", &aliases));
+    }
+}
@@ -0,0 +1,218 @@
+use crate::models::{
+    params::{self, session::Input},
+    payload::{Generated, SessionState, SessionStatus, VefifyResult},
+};
+use log::{debug, info};
+use std::{
+    collections::HashMap,
+    sync::LazyLock,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::RwLock;
+
+type Sessions = HashMap<String, Session>;
+static SESSIONS: LazyLock<RwLock<Sessions>> = LazyLock::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    // 会话参数
+    pub input: Input,
+    // 通过所需的成功轮数
+    pub pass_threshold: usize,
+    // 当前轮次索引
+    pub round: usize,
+    // 成功轮数
+    pub successes: usize,
+    // 失败轮数
+    pub failures: usize,
+    // 当前轮次的验证 ID
+    pub unique_id: Option<String>,
+    // 会话状态
+    pub status: SessionStatus,
+    // 过期于
+    pub expires_at: SystemTime,
+}
+
+impl Session {
+    pub fn new(input: Input, pass_threshold: usize, ttl_secs: u64) -> Self {
+        Session {
+            input,
+            pass_threshold,
+            round: 0,
+            successes: 0,
+            failures: 0,
+            unique_id: None,
+            status: SessionStatus::Pending,
+            expires_at: SystemTime::now() + Duration::from_secs(ttl_secs),
+        }
+    }
+
+    pub fn total_rounds(&self) -> usize {
+        self.input.rounds.len()
+    }
+
+    // 剩余的存活秒数
+    pub fn remaining_ttl_secs(&self) -> Option<u64> {
+        self.expires_at
+            .duration_since(SystemTime::now())
+            .ok()
+            .map(|remaining| remaining.as_secs())
+            .filter(|secs| *secs > 0)
+    }
+
+    // 当前轮次的生成参数（命名空间、TTL 和终端用户由会话统一提供）
+    pub fn round_input(&self, ttl_secs: u64) -> Option<params::Input> {
+        let mut input = self.input.rounds.get(self.round)?.clone();
+        input.namespace = self.input.namespace.clone();
+        input.ttl_secs = Some(ttl_secs);
+        input.subject_id = self.input.subject_id.clone();
+
+        Some(input)
+    }
+
+    /// 记录当前轮次的结果，返回是否需要进入下一轮。
+    pub fn advance(&mut self, passed: bool) -> bool {
+        if passed {
+            self.successes += 1;
+        } else {
+            self.failures += 1;
+        }
+        self.unique_id = None;
+
+        if self.successes >= self.pass_threshold {
+            self.status = SessionStatus::Passed;
+            false
+        } else if self.failures > self.total_rounds() - self.pass_threshold {
+            // 剩余轮次已不足以通过
+            self.status = SessionStatus::Failed;
+            false
+        } else {
+            self.round += 1;
+            true
+        }
+    }
+
+    pub fn state(
+        &self,
+        session_id: &str,
+        last_result: Option<VefifyResult>,
+        generated: Option<Generated>,
+    ) -> SessionState {
+        SessionState {
+            session_id: session_id.to_string(),
+            status: self.status,
+            round: self.round + 1,
+            total_rounds: self.total_rounds(),
+            successes: self.successes,
+            pass_threshold: self.pass_threshold,
+            unique_id: self.unique_id.clone(),
+            last_result,
+            generated,
+        }
+    }
+}
+
+pub async fn add_session(session_id: String, session: Session) {
+    SESSIONS.write().await.insert(session_id, session);
+}
+
+pub async fn get_session(session_id: &str) -> Option<Session> {
+    SESSIONS
+        .read()
+        .await
+        .get(session_id)
+        .filter(|session| session.expires_at > SystemTime::now())
+        .cloned()
+}
+
+pub async fn update_session(session_id: &str, session: Session) {
+    let mut sessions = SESSIONS.write().await;
+    if let Some(stored) = sessions.get_mut(session_id) {
+        *stored = session;
+    }
+}
+
+// 清理已过期的会话
+pub async fn cleanup_expired() {
+    let now = SystemTime::now();
+    let mut sessions = SESSIONS.write().await;
+    let before = sessions.len();
+    sessions.retain(|session_id, session| {
+        let alive = session.expires_at > now;
+        if !alive {
+            debug!("Removed expired session: {session_id}");
+        }
+
+        alive
+    });
+    let removed_count = before - sessions.len();
+    if removed_count > 0 {
+        info!("Removed {removed_count} expired session(s)");
+    } else {
+        debug!("No expired sessions to remove");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_session(rounds: usize, pass_threshold: usize) -> Session {
+        let input = Input {
+            namespace: "test".to_string(),
+            ttl_secs: None,
+            subject_id: None,
+            pass_threshold: None,
+            rounds: vec![params::Input::classic(""); rounds],
+        };
+
+        Session::new(input, pass_threshold, 60)
+    }
+
+    #[test]
+    fn test_advance() {
+        // 三轮两胜：连续两次成功即通过
+        let mut session = new_session(3, 2);
+        assert!(session.advance(true));
+        assert!(!session.advance(true));
+        assert_eq!(session.status, SessionStatus::Passed);
+
+        // 三轮两胜：两次失败即无法通过
+        let mut session = new_session(3, 2);
+        assert!(session.advance(false));
+        assert!(session.advance(true));
+        assert!(!session.advance(false));
+        assert_eq!(session.status, SessionStatus::Failed);
+        assert_eq!(session.round, 2);
+
+        // 全部轮次都必须成功
+        let mut session = new_session(2, 2);
+        assert!(!session.advance(false));
+        assert_eq!(session.status, SessionStatus::Failed);
+    }
+
+    #[test]
+    fn test_parse_rounds() {
+        // 轮次参数的 namespace 由会话提供，单独生成时仍然必须提供
+        let round = r#"{"special_params": {"type": "classic"}}"#;
+        let input: Input =
+            serde_json::from_str(&format!(r#"{{"namespace": "test", "rounds": [{round}]}}"#))
+                .unwrap();
+        assert_eq!(input.rounds[0].namespace, "test");
+        assert!(serde_json::from_str::<params::Input>(round).is_err());
+    }
+
+    #[test]
+    fn test_round_input() {
+        let mut session = new_session(2, 2);
+        session.input.subject_id = Some("10086".to_string());
+        let input = session.round_input(30).unwrap();
+
+        assert_eq!(input.namespace, "test");
+        assert_eq!(input.ttl_secs, Some(30));
+        assert_eq!(input.subject_id, Some("10086".to_string()));
+
+        session.round = 2;
+        assert!(session.round_input(30).is_none());
+    }
+}
@@ -1,4 +1,7 @@
-use crate::models::params::verification::Answer;
+use crate::models::{
+    params::{Input, verification::Answer},
+    payload::VefifyResult,
+};
 use log::{debug, info};
 use std::{
     cmp::Ordering,
@@ -8,7 +11,7 @@ use std::{
 };
 use tokio::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-type Store = HashMap<Arc<String>, Answer>;
+type Store = HashMap<Arc<String>, Entry>;
 static STORE: LazyLock<RwLock<Store>> = LazyLock::new(|| RwLock::new(HashMap::new()));
 static CACHE: LazyLock<RwLock<Cache>> = LazyLock::new(|| RwLock::new(Cache::new()));
 
@@ -82,22 +85,154 @@ pub async fn queue_size() -> usize {
     cache().await.queue_size().await
 }
 
-pub async fn add_cache(unique_id: String, answer: Answer, ttl_secs: u64) {
+pub async fn add_cache(unique_id: String, mut entry: Entry, ttl_secs: u64) {
     let key = Arc::new(unique_id);
     let mut store = use_store().await;
     let cache = cache().await;
+    let expiring = Expiring::new(key.clone(), ttl_secs);
+    entry.expires_at = expiring.expires_at;
     // 将答案存入缓存
-    store.insert(key.clone(), answer);
+    store.insert(key, entry);
     // 添加到过期检查
-    cache.add(Expiring::new(key, ttl_secs)).await;
+    cache.add(expiring).await;
 }
 
-pub async fn get_cache(unique_id: &str) -> Option<Answer> {
+pub async fn get_cache(unique_id: &str) -> Option<Entry> {
     let store = store().await;
 
     store.get(&Arc::new(unique_id.to_string())).cloned()
 }
 
+// 记录一次验证尝试及其结果
+pub async fn record_attempt(unique_id: &str, outcome: &VefifyResult) {
+    let mut store = use_store().await;
+    if let Some(entry) = store.get_mut(&Arc::new(unique_id.to_string())) {
+        entry.attempts += 1;
+        entry.outcome = Some(outcome.clone());
+    }
+}
+
+// 用重新生成的文件和答案替换缓存，保留 TTL 和验证记录
+pub async fn refresh_cache(
+    unique_id: &str,
+    files: Vec<String>,
+    answer: Answer,
+    right_index: Option<usize>,
+) -> Option<Entry> {
+    let mut store = use_store().await;
+    let entry = store.get_mut(&Arc::new(unique_id.to_string()))?;
+    let replaced = entry.clone();
+    entry.files = files;
+    entry.answer = answer;
+    entry.right_index = right_index;
+    entry.refreshed_at = Some(SystemTime::now());
+
+    Some(replaced)
+}
+
+pub async fn remove_cache(unique_id: &str) -> Option<Entry> {
+    let mut store = use_store().await;
+
+    // 过期队列中残留的 ID 会在到期时被忽略
+    store.remove(&Arc::new(unique_id.to_string()))
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    // 验证类型
+    pub kind: &'static str,
+    // 命名空间
+    pub namespace: String,
+    // 输出文件名
+    pub files: Vec<String>,
+    // 正确答案（原生形式）
+    pub answer: Answer,
+    // 正确候选项的索引（未生成候选项时为空）
+    pub right_index: Option<usize>,
+    // 生成于
+    pub created_at: SystemTime,
+    // 刷新于
+    pub refreshed_at: Option<SystemTime>,
+    // 过期于（加入缓存时设置）
+    pub expires_at: SystemTime,
+    // 最短作答时间（毫秒）
+    pub min_solve_ms: Option<u64>,
+    // 最长作答时间（毫秒）
+    pub max_solve_ms: Option<u64>,
+    // 绑定的终端用户标识
+    pub subject_id: Option<String>,
+    // 生成参数（刷新时用于重新生成）
+    pub input: Option<Input>,
+    // 已验证次数
+    pub attempts: usize,
+    // 最近一次的验证结果
+    pub outcome: Option<VefifyResult>,
+}
+
+impl Entry {
+    pub fn new(kind: &'static str, namespace: String, files: Vec<String>, answer: Answer) -> Self {
+        let created_at = SystemTime::now();
+        Entry {
+            kind,
+            namespace,
+            files,
+            answer,
+            right_index: None,
+            created_at,
+            refreshed_at: None,
+            expires_at: created_at,
+            min_solve_ms: None,
+            max_solve_ms: None,
+            subject_id: None,
+            input: None,
+            attempts: 0,
+            outcome: None,
+        }
+    }
+
+    pub fn solve_window(mut self, min_solve_ms: Option<u64>, max_solve_ms: Option<u64>) -> Self {
+        self.min_solve_ms = min_solve_ms;
+        self.max_solve_ms = max_solve_ms;
+
+        self
+    }
+
+    pub fn bind_subject(mut self, subject_id: Option<String>) -> Self {
+        self.subject_id = subject_id;
+
+        self
+    }
+
+    pub fn with_right_index(mut self, right_index: Option<usize>) -> Self {
+        self.right_index = right_index;
+
+        self
+    }
+
+    pub fn regenerable(mut self, input: Input) -> Self {
+        self.input = Some(input);
+
+        self
+    }
+
+    // 从生成（或最近一次刷新）到现在经过的毫秒数
+    pub fn elapsed_ms(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(self.refreshed_at.unwrap_or(self.created_at))
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+
+    // 剩余的存活秒数
+    pub fn remaining_ttl_secs(&self) -> Option<u64> {
+        self.expires_at
+            .duration_since(SystemTime::now())
+            .ok()
+            .map(|remaining| remaining.as_secs())
+            .filter(|secs| *secs > 0)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Expiring {
     // 过期于